@@ -0,0 +1,150 @@
+use std::fmt;
+
+use bech32::{FromBase32, ToBase32, Variant};
+
+use crate::prelude::*;
+
+/// Bech32 prefix of the Archway addresses.
+pub const PREFIX: &str = "archway";
+
+/// Length in bytes of the accounts derived from a public key.
+const ACCOUNT_LENGTH: usize = 20;
+/// Length in bytes of the smart contract addresses.
+const CONTRACT_LENGTH: usize = 32;
+
+/// A valid Archway account address, in lowercase.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Address {
+    address: String,
+    bytes: Vec<u8>,
+}
+
+/// Why an address can't be used as a participant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidAddress {
+    /// Not a bech32 string.
+    Encoding(bech32::Error),
+    /// A bech32 address for another chain.
+    Prefix(String),
+    /// A smart contract, which can't take part in the drop camp.
+    Contract,
+    /// A bech32 string with an unexpected length.
+    Length(usize),
+}
+
+impl fmt::Display for InvalidAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Encoding(err) => write!(f, "invalid bech32 encoding: {}", err),
+            Self::Prefix(prefix) => write!(f, "unexpected prefix {}", prefix),
+            Self::Contract => write!(f, "contract address"),
+            Self::Length(len) => write!(f, "unexpected length of {} bytes", len),
+        }
+    }
+}
+
+impl std::error::Error for InvalidAddress {}
+
+impl Address {
+    /// Validates an Archway account address, ignoring its case.
+    pub fn parse(address: &str) -> std::result::Result<Self, InvalidAddress> {
+        let address = address.trim().to_lowercase();
+        let (prefix, data, _) = bech32::decode(&address).map_err(InvalidAddress::Encoding)?;
+        if prefix != PREFIX {
+            return Err(InvalidAddress::Prefix(prefix));
+        }
+
+        let bytes = Vec::<u8>::from_base32(&data).map_err(InvalidAddress::Encoding)?;
+        match bytes.len() {
+            ACCOUNT_LENGTH => std::result::Result::Ok(Self { address, bytes }),
+            CONTRACT_LENGTH => Err(InvalidAddress::Contract),
+            len => Err(InvalidAddress::Length(len)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.address
+    }
+
+    /// Encodes the same account with the prefix of another Cosmos chain.
+    ///
+    /// Only valid for chains sharing the Archway coin type.
+    pub fn derive(&self, prefix: &str) -> Result<String> {
+        let address = bech32::encode(prefix, self.bytes.to_base32(), Variant::Bech32)?;
+        Ok(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(prefix: &str, bytes: &[u8]) -> String {
+        bech32::encode(prefix, bytes.to_base32(), Variant::Bech32).unwrap()
+    }
+
+    #[test]
+    fn parses_account_ignoring_case() {
+        let account = encode(PREFIX, &[1; ACCOUNT_LENGTH]);
+
+        let address = Address::parse(&format!(" {} ", account.to_uppercase())).unwrap();
+        assert_eq!(address.as_str(), account);
+    }
+
+    #[test]
+    fn rejects_wrong_prefix() {
+        let address = encode("cosmos", &[1; ACCOUNT_LENGTH]);
+        assert_eq!(
+            Address::parse(&address),
+            Err(InvalidAddress::Prefix("cosmos".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_contract() {
+        let address = encode(PREFIX, &[1; CONTRACT_LENGTH]);
+        assert_eq!(Address::parse(&address), Err(InvalidAddress::Contract));
+    }
+
+    #[test]
+    fn rejects_bad_length() {
+        let address = encode(PREFIX, &[1; 16]);
+        assert_eq!(Address::parse(&address), Err(InvalidAddress::Length(16)));
+    }
+
+    #[test]
+    fn rejects_bad_encoding() {
+        let mut address = encode(PREFIX, &[1; ACCOUNT_LENGTH]);
+        // breaks the checksum
+        let last = if address.ends_with('q') { 'p' } else { 'q' };
+        address.pop();
+        address.push(last);
+
+        assert!(matches!(
+            Address::parse(&address),
+            Err(InvalidAddress::Encoding(_))
+        ));
+        assert!(matches!(
+            Address::parse("not an address"),
+            Err(InvalidAddress::Encoding(_))
+        ));
+    }
+
+    #[test]
+    fn derives_same_account() {
+        let address = Address::parse(&encode(PREFIX, &[1; ACCOUNT_LENGTH])).unwrap();
+
+        let derived = address.derive("cosmos").unwrap();
+        assert_eq!(derived, encode("cosmos", &[1; ACCOUNT_LENGTH]));
+        assert_eq!(
+            Address::parse(&derived),
+            Err(InvalidAddress::Prefix("cosmos".to_string()))
+        );
+    }
+}
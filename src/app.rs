@@ -1,9 +1,18 @@
-use std::path::PathBuf;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
-use crate::exporters;
+use crate::block::{BlockId, Blocks};
+use crate::checkpoint::Checkpoint;
+use crate::context::ContextBuilder;
+use crate::diff;
+use crate::exporters::{self, Aggregation, ErrorPolicy, ExporterKind, Inspection};
+use crate::manifest::Manifest;
+use crate::participant;
 use crate::prelude::*;
+use crate::queriers::soulbound::SocialScorePolicy;
 
 use url::Url;
 
@@ -16,7 +25,11 @@ const TOKEN_MAP_FILE: &str = "tokens.toml";
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
+#[command(subcommand_negates_reqs = true)]
 pub struct App {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Url for the RPC endpoint.
     #[arg(long, default_value = RPC_URL)]
     pub rpc_url: Url,
@@ -30,21 +43,69 @@ pub struct App {
     #[arg(long)]
     pub height: Option<u64>,
 
+    /// Runs the operation on the last block at or before a RFC 3339 date and time,
+    /// e.g. `2024-05-01T00:00:00Z`.
+    #[arg(long, conflicts_with_all = ["height", "resume"])]
+    pub at: Option<DateTime<Utc>>,
+
+    /// Runs the exporters at several blocks, given as a `start:end:step` range of heights,
+    /// or as heights and dates (`2024-05-01` or RFC 3339) separated by `,`.
+    /// The scores are written per height and aggregated before ranking.
+    #[arg(long, conflicts_with_all = ["height", "at", "resume"])]
+    pub heights: Option<Blocks>,
+
+    /// How the scores at each of the `--heights` are aggregated before ranking.
+    #[arg(long, value_enum, default_value_t)]
+    pub aggregation: Aggregation,
+
+    /// Only searches the transactions at or after this block height,
+    /// otherwise the whole history available in the RPC node.
+    #[arg(long)]
+    pub since_height: Option<u64>,
+
     /// Address for the soulbound token cw721 smart contract.
+    /// Required to export unless the participants come only from `--addresses`.
+    #[arg(long, required_unless_present = "addresses")]
+    pub soulbound_address: Option<String>,
+
+    /// Path for a `.txt` file with one address per line, or a `.csv` file with the
+    /// addresses in the first column, used as participants instead of the soulbound token owners.
+    #[arg(long)]
+    pub addresses: Option<PathBuf>,
+
+    /// Merges the `--addresses` list with the soulbound token owners.
+    #[arg(long, requires_all = ["addresses", "soulbound_address"])]
+    pub merge: bool,
+
+    /// Writes the participant accounts encoded with the given bech32 prefixes of other
+    /// Cosmos chains, separated by `,`, to `derived-addresses.csv`.
+    #[arg(long, value_delimiter = ',')]
+    pub derive_prefixes: Vec<String>,
+
+    /// Looks for clusters of participants likely controlled by the same person,
+    /// writing them to `sybil-clusters.csv`. Requires an RPC node indexing transactions.
     #[arg(long)]
-    pub soulbound_address: String,
+    pub sybil: bool,
 
     /// Address for the ArchID registry smart contract.
+    /// Required by the `archid` exporter.
     #[arg(long)]
-    pub archid_address: String,
+    pub archid_address: Option<String>,
 
     /// Address for the Liquid Finance cw20 smart contract.
+    /// Required by the `liquid-finance` exporter.
     #[arg(long)]
-    pub liquid_finance_address: String,
+    pub liquid_finance_address: Option<String>,
+
+    /// Address for the Liquid Finance staking smart contract, used to value `sARCH` in `ARCH`.
+    /// Required by the `liquid-finance` exporter.
+    #[arg(long)]
+    pub liquid_finance_staking_address: Option<String>,
 
     /// Url for the Astrovault liquidity pools API.
+    /// Required by the `astrovault` exporter.
     #[arg(long)]
-    pub astrovault_url: Url,
+    pub astrovault_url: Option<Url>,
 
     /// Limits the number of requests per second to the Astrovault API.
     #[arg(long)]
@@ -54,6 +115,11 @@ pub struct App {
     #[arg(long)]
     pub astrovault_api_key: Option<String>,
 
+    /// Uses the Astrovault batched wallets and positions endpoints, querying this many addresses per request.
+    /// Otherwise, or when a batch fails, each address is queried individually.
+    #[arg(long)]
+    pub astrovault_batch_size: Option<NonZeroUsize>,
+
     /// Url for the CoinGecko API.
     #[arg(long, default_value = COINGECKO_URL)]
     pub coingecko_url: Url,
@@ -66,38 +132,322 @@ pub struct App {
     #[arg(long, default_value = TOKEN_MAP_FILE)]
     pub token_map: PathBuf,
 
+    /// Path for a file defining more exporters, such as the CW20 tokens, the CW721
+    /// collections or the smart queries to export.
+    /// Required by the `cw20`, `cw721` and `smart-query` exporters.
+    #[arg(long)]
+    pub exporters: Option<PathBuf>,
+
+    /// Only runs the given exporters, separated by `,`.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub only: Vec<ExporterKind>,
+
+    /// Skips the given exporters, separated by `,`.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub skip: Vec<ExporterKind>,
+
+    /// How the social score is computed for addresses holding multiple soulbound tokens.
+    #[arg(long, value_enum, default_value_t)]
+    pub social_score_policy: SocialScorePolicy,
+
     /// Directory path to output the CSV files.
+    /// Required unless running a subcommand.
     #[arg(short, long)]
-    pub output: PathBuf,
+    pub output: Option<PathBuf>,
+
+    /// Resumes an interrupted export from the checkpoint in the output directory,
+    /// reusing its block height and skipping the work already completed.
+    #[arg(long)]
+    pub resume: bool,
+
+    /// What to do when an exporter fails for an address.
+    /// Failures are written to `failures.csv` unless aborting.
+    #[arg(long, value_enum, default_value_t)]
+    pub on_error: ErrorPolicy,
+
+    /// Maximum ratio of failed exports, between 0 and 1, before the execution is considered failed.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_failure_rate)]
+    pub max_failure_rate: f64,
 
     /// Sets the log level.
     #[arg(long, value_name = "LEVEL", default_value = "info")]
     pub log_level: tracing::metadata::LevelFilter,
 }
 
+#[derive(Subcommand)]
+pub enum Command {
+    /// Runs the selected exporters for a single address and prints the results,
+    /// without writing any file.
+    Inspect(InspectArgs),
+    /// Compares the CSV files of two exports of the selected exporters,
+    /// printing a summary and writing the changes to `diff.csv`.
+    Diff(DiffArgs),
+    /// Checks the files of an export against the checksums of its `manifest.json`.
+    Verify(VerifyArgs),
+}
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Address to inspect.
+    pub address: String,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Directory of the previous export.
+    pub old: PathBuf,
+
+    /// Directory of the new export, where `diff.csv` is written unless `--output` is given.
+    pub new: PathBuf,
+
+    /// Only reports ranking changes greater than this value.
+    #[arg(long, default_value_t = 0.0)]
+    pub threshold: f64,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Directory of the export.
+    pub directory: PathBuf,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable table.
+    #[default]
+    Table,
+    Json,
+}
+
 impl App {
     pub async fn run(&self) -> Result<()> {
-        let ctx = Context::builder()
+        let mut exporters = ExporterKind::select(&self.only, &self.skip);
+        // the exporters defined in a config file only run by default when it's given
+        if self.exporters.is_none() && self.only.is_empty() {
+            exporters.retain(|kind| !kind.is_custom());
+        }
+
+        match &self.command {
+            Some(Command::Diff(args)) => self.diff(&exporters, args).await,
+            Some(Command::Verify(args)) => verify(args),
+            Some(Command::Inspect(args)) => {
+                self.validate(&exporters)?;
+                self.inspect(exporters, args).await
+            }
+            None => {
+                self.validate(&exporters)?;
+                self.export(exporters).await
+            }
+        }
+    }
+
+    async fn diff(&self, exporters: &[ExporterKind], args: &DiffArgs) -> Result<()> {
+        let output = self.output.as_ref().unwrap_or(&args.new);
+        std::fs::create_dir_all(output)?;
+
+        diff::run(&args.old, &args.new, output, exporters, args.threshold).await
+    }
+
+    async fn export(&self, exporters: Vec<ExporterKind>) -> Result<()> {
+        let output = self
+            .output
+            .clone()
+            .ok_or(anyhow!("--output is required to export"))?;
+
+        // the participants of a history export are the ones at its last block
+        let block = match &self.heights {
+            Some(Blocks(blocks)) => blocks.last().copied(),
+            None if self.resume => Some(BlockId::Height(self.checkpoint_height(&output)?)),
+            None => self.block(),
+        };
+
+        let ctx = self
+            .context(block, exporters)
+            .resume(self.resume)
+            .output(Some(output))
+            .build()
+            .await?;
+        let ctx = Arc::new(ctx);
+
+        match &self.heights {
+            Some(Blocks(blocks)) => exporters::run_history(ctx, blocks, self.aggregation).await?,
+            None => exporters::run(ctx).await?,
+        }
+
+        Ok(())
+    }
+
+    async fn inspect(&self, exporters: Vec<ExporterKind>, args: &InspectArgs) -> Result<()> {
+        let ctx = self.context(self.block(), exporters).build().await?;
+        let ctx = Arc::new(ctx);
+
+        // addresses without soulbound tokens are inspected with a zero social score
+        let owner = participant::one(ctx.clone(), &args.address).await?;
+
+        let inspections = exporters::inspect(ctx.clone(), &owner).await?;
+        let total: f32 = inspections.iter().map(Inspection::weighted_ranking).sum();
+        let block = &ctx.cosmos.block;
+
+        match args.format {
+            OutputFormat::Table => {
+                println!("address: {}", owner.address);
+                println!("height:  {} ({})", block.height, block.time);
+                println!();
+                println!(
+                    "{:<16} {:>10} {:>8} {:>10}",
+                    "activity", "ranking", "weight", "weighted"
+                );
+                for inspection in inspections.iter() {
+                    println!(
+                        "{:<16} {:>10.2} {:>8.2} {:>10.2}",
+                        inspection.exporter,
+                        inspection.ranking,
+                        inspection.weight,
+                        inspection.weighted_ranking()
+                    );
+                }
+                println!("{:<16} {:>30.2}", "total", total);
+
+                for inspection in inspections.iter() {
+                    println!();
+                    println!("{}:", inspection.exporter);
+                    println!("{}", serde_json::to_string_pretty(&inspection.data)?);
+                }
+            }
+            OutputFormat::Json => {
+                let report = serde_json::json!({
+                    "address": owner.address,
+                    "height": block.height.value(),
+                    "time": block.time.to_rfc3339(),
+                    "activities": inspections,
+                    "total": total,
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn context(&self, block: Option<BlockId>, exporters: Vec<ExporterKind>) -> ContextBuilder {
+        Context::builder()
             .rpc(self.rpc_url.clone(), self.rpc_req_second)
-            .height(self.height)
+            .block(block)
+            .since_height(self.since_height)
             .soulbound_address(self.soulbound_address.clone())
+            .addresses(self.addresses.clone(), self.merge)
+            .derive_prefixes(self.derive_prefixes.clone())
+            .sybil(self.sybil)
             .archid_address(self.archid_address.clone())
             .liquid_finance_address(self.liquid_finance_address.clone())
+            .liquid_finance_staking_address(self.liquid_finance_staking_address.clone())
             .astrovault(
                 self.astrovault_url.clone(),
                 self.astrovault_req_second,
                 self.astrovault_api_key.clone(),
             )
+            .astrovault_batch_size(self.astrovault_batch_size.map(NonZeroUsize::get))
             .coingecko(self.coingecko_url.clone())
+            .exporters(exporters)
             .ranking_path(self.ranking.clone())
             .token_map_path(self.token_map.clone())
-            .output(self.output.clone())
-            .build()
-            .await?;
-        let ctx = Arc::new(ctx);
+            .custom_exporters_path(self.exporters.clone())
+            .social_score_policy(self.social_score_policy)
+            .on_error(self.on_error, self.max_failure_rate)
+    }
+
+    /// Checks the arguments required by the selected exporters.
+    fn validate(&self, exporters: &[ExporterKind]) -> Result<()> {
+        if exporters.is_empty() {
+            return Err(anyhow!("no exporters selected"));
+        }
 
-        exporters::run(ctx).await?;
+        for exporter in exporters {
+            let missing = match exporter {
+                ExporterKind::Archid => self.archid_address.is_none().then_some("--archid-address"),
+                ExporterKind::LiquidFinance => {
+                    if self.liquid_finance_address.is_none() {
+                        Some("--liquid-finance-address")
+                    } else {
+                        self.liquid_finance_staking_address
+                            .is_none()
+                            .then_some("--liquid-finance-staking-address")
+                    }
+                }
+                ExporterKind::Astrovault => {
+                    self.astrovault_url.is_none().then_some("--astrovault-url")
+                }
+                kind if kind.is_custom() => self.exporters.is_none().then_some("--exporters"),
+                _ => None,
+            };
+
+            if let Some(arg) = missing {
+                return Err(anyhow!(
+                    "{} is required by the {:?} exporter",
+                    arg,
+                    exporter
+                ));
+            }
+        }
 
         Ok(())
     }
+
+    /// Block requested with `--height` or `--at`.
+    fn block(&self) -> Option<BlockId> {
+        match (self.height, self.at) {
+            (Some(height), _) => Some(BlockId::Height(height)),
+            (None, Some(time)) => Some(BlockId::Time(time)),
+            (None, None) => None,
+        }
+    }
+
+    fn checkpoint_height(&self, output: &Path) -> Result<u64> {
+        let height = Checkpoint::height(output)?;
+
+        if let Some(requested) = self.height.filter(|&requested| requested != height) {
+            return Err(anyhow!(
+                "checkpoint was created at height {}, but height {} was requested",
+                height,
+                requested
+            ));
+        }
+
+        Ok(height)
+    }
+}
+
+fn verify(args: &VerifyArgs) -> Result<()> {
+    let manifest = Manifest::read(&args.directory)?;
+    let problems = manifest.verify(&args.directory)?;
+
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!("{} files failed verification", problems.len()));
+    }
+
+    println!(
+        "{} files verified, exported at height {} ({})",
+        manifest.files.len(),
+        manifest.metadata.height,
+        manifest.metadata.time
+    );
+
+    Ok(())
+}
+
+/// Parses a failure rate, which must be between 0 and 1.
+fn parse_failure_rate(value: &str) -> Result<f64> {
+    let rate: f64 = value.parse()?;
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(anyhow!("{} is not between 0 and 1", rate));
+    }
+
+    Ok(rate)
 }
@@ -23,3 +23,147 @@ impl TryFrom<Header> for Block {
         Ok(Self { height, time })
     }
 }
+
+/// A block selected by its height or by its time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlockId {
+    Height(u64),
+    /// The last block at or before the given time.
+    Time(DateTime<Utc>),
+}
+
+impl std::str::FromStr for BlockId {
+    type Err = anyhow::Error;
+
+    /// Parses a block height, a RFC 3339 date and time, or a date at midnight UTC.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let std::result::Result::Ok(height) = s.parse::<u64>() {
+            return Ok(Self::Height(height));
+        }
+        if let std::result::Result::Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self::Time(time.with_timezone(&Utc)));
+        }
+        if let std::result::Result::Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(Self::Time(date.and_time(NaiveTime::MIN).and_utc()));
+        }
+
+        Err(anyhow!("invalid block height or date: {}", s))
+    }
+}
+
+/// A list of blocks, given as a `start:end:step` range of heights
+/// or as heights and dates separated by `,`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blocks(pub Vec<BlockId>);
+
+impl std::str::FromStr for Blocks {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(blocks) = Self::parse_range(s)? {
+            return Ok(blocks);
+        }
+
+        let blocks = s
+            .split(',')
+            .map(|block| block.trim().parse())
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self(blocks))
+    }
+}
+
+impl Blocks {
+    /// Parses a `start:end:step` range of heights, which can't be confused with
+    /// the `:` of the dates since all its parts are integers.
+    fn parse_range(s: &str) -> Result<Option<Self>> {
+        if s.contains(',') {
+            return Ok(None);
+        }
+
+        let parts: Option<Vec<u64>> = s.split(':').map(|part| part.trim().parse().ok()).collect();
+        let Some([start, end, step]) = parts.as_deref() else {
+            return Ok(None);
+        };
+        if *step == 0 || start > end {
+            bail!("invalid height range: {}", s);
+        }
+
+        let blocks = (*start..=*end)
+            .step_by(*step as usize)
+            .map(BlockId::Height)
+            .collect();
+
+        Ok(Some(Self(blocks)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn time(s: &str) -> BlockId {
+        BlockId::Time(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc))
+    }
+
+    #[test]
+    fn parses_height_range() {
+        let blocks = Blocks::from_str("100:300:100").unwrap();
+        assert_eq!(
+            blocks.0,
+            vec![
+                BlockId::Height(100),
+                BlockId::Height(200),
+                BlockId::Height(300)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_height_range() {
+        assert!(Blocks::from_str("300:100:100").is_err());
+        assert!(Blocks::from_str("100:300:0").is_err());
+    }
+
+    #[test]
+    fn parses_height_list() {
+        let blocks = Blocks::from_str("100, 200,300").unwrap();
+        assert_eq!(
+            blocks.0,
+            vec![
+                BlockId::Height(100),
+                BlockId::Height(200),
+                BlockId::Height(300)
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_dates() {
+        let blocks = Blocks::from_str("2024-05-01,2024-06-01").unwrap();
+        assert_eq!(
+            blocks.0,
+            vec![time("2024-05-01T00:00:00Z"), time("2024-06-01T00:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn parses_rfc3339_times() {
+        let blocks = Blocks::from_str("2024-05-01T00:00:00Z").unwrap();
+        assert_eq!(blocks.0, vec![time("2024-05-01T00:00:00Z")]);
+
+        let blocks = Blocks::from_str("2024-05-01T12:30:00+02:00,1000").unwrap();
+        assert_eq!(
+            blocks.0,
+            vec![time("2024-05-01T10:30:00Z"), BlockId::Height(1000)]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_blocks() {
+        assert!(Blocks::from_str("yesterday").is_err());
+        assert!(Blocks::from_str("100:200").is_err());
+    }
+}
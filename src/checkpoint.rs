@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::participant::Participant;
+
+const STATE_FILE: &str = "checkpoint.json";
+const PROGRESS_FILE: &str = "checkpoint.log";
+const SEPARATOR: char = ';';
+
+#[derive(Debug, Deserialize, Serialize)]
+struct State {
+    height: u64,
+    owners: Vec<Participant>,
+}
+
+/// Export progress persisted in the output directory.
+///
+/// The participants and the pinned height are written once when the export
+/// starts, while every completed `(exporter, address)` pair is appended
+/// to a log, so an interrupted export can be resumed.
+#[derive(Debug)]
+pub struct Checkpoint {
+    state: State,
+    completed: HashSet<(String, String)>,
+    progress: Mutex<File>,
+}
+
+impl Checkpoint {
+    /// Reads the block height pinned by the checkpoint in the output directory.
+    pub fn height(output: &Path) -> Result<u64> {
+        Ok(Self::read_state(output)?.height)
+    }
+
+    /// Starts a new checkpoint, discarding any previous progress.
+    pub async fn create(output: &Path, height: u64, owners: Vec<Participant>) -> Result<Self> {
+        let state = State { height, owners };
+        tokio::fs::write(state_path(output), serde_json::to_vec(&state)?).await?;
+        let progress = File::create(progress_path(output)).await?;
+        tracing::info!(height, "checkpoint created");
+
+        Ok(Self {
+            state,
+            completed: HashSet::new(),
+            progress: Mutex::new(progress),
+        })
+    }
+
+    /// Loads the checkpoint from the output directory, including the completed work.
+    pub async fn resume(output: &Path) -> Result<Self> {
+        let state = Self::read_state(output)?;
+
+        let path = progress_path(output);
+        let completed: HashSet<(String, String)> = match tokio::fs::read_to_string(&path).await {
+            Ok(log) => log
+                .lines()
+                .filter_map(|line| line.split_once(SEPARATOR))
+                .map(|(exporter, address)| (exporter.to_string(), address.to_string()))
+                .collect(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let progress = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        tracing::info!(
+            height = state.height,
+            completed = completed.len(),
+            "resuming from checkpoint"
+        );
+
+        Ok(Self {
+            state,
+            completed,
+            progress: Mutex::new(progress),
+        })
+    }
+
+    pub fn owners(&self) -> &[Participant] {
+        &self.state.owners
+    }
+
+    pub fn is_completed(&self, exporter: &str, address: &str) -> bool {
+        self.completed
+            .contains(&(exporter.to_string(), address.to_string()))
+    }
+
+    /// Records that the exporter finished with the address.
+    pub async fn complete(&self, exporter: &str, address: &str) -> Result<()> {
+        let line = format!("{exporter}{SEPARATOR}{address}\n");
+
+        let mut progress = self.progress.lock().await;
+        progress.write_all(line.as_bytes()).await?;
+        progress.flush().await?;
+
+        Ok(())
+    }
+
+    fn read_state(output: &Path) -> Result<State> {
+        let path = state_path(output);
+        let data = std::fs::read(&path)
+            .map_err(|e| anyhow!("failed to read checkpoint {}: {}", path.display(), e))?;
+        let state = serde_json::from_slice(&data)?;
+
+        Ok(state)
+    }
+}
+
+fn state_path(output: &Path) -> PathBuf {
+    output.join(STATE_FILE)
+}
+
+fn progress_path(output: &Path) -> PathBuf {
+    output.join(PROGRESS_FILE)
+}
@@ -1,14 +1,59 @@
 use std::time::Duration;
 
 use anyhow::{anyhow, Result};
-use reqwest::header;
-use serde::Deserialize;
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use tower::limit::RateLimitLayer;
 use tower::util::BoxService;
 use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
 use url::Url;
 
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Errors returned by the Astrovault API, mapped from the response status.
+#[derive(Debug)]
+pub enum AstrovaultError {
+    Unauthorized(StatusCode),
+    NotFound,
+    RateLimited,
+    Server(StatusCode),
+    Unexpected(StatusCode),
+}
+
+impl AstrovaultError {
+    fn from_status(status: StatusCode) -> Option<Self> {
+        match status {
+            status if status.is_success() => None,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Some(Self::Unauthorized(status)),
+            StatusCode::NOT_FOUND => Some(Self::NotFound),
+            StatusCode::TOO_MANY_REQUESTS => Some(Self::RateLimited),
+            status if status.is_server_error() => Some(Self::Server(status)),
+            status => Some(Self::Unexpected(status)),
+        }
+    }
+
+    /// Whether the request may succeed if retried later.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::RateLimited | Self::Server(_))
+    }
+}
+
+impl std::fmt::Display for AstrovaultError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized(status) => write!(f, "unauthorized, check the API key ({status})"),
+            Self::NotFound => write!(f, "wallet not found"),
+            Self::RateLimited => write!(f, "rate limited"),
+            Self::Server(status) => write!(f, "server error ({status})"),
+            Self::Unexpected(status) => write!(f, "unexpected response status ({status})"),
+        }
+    }
+}
+
+impl std::error::Error for AstrovaultError {}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WalletStats {
@@ -23,10 +68,48 @@ pub struct WalletTvl {
     pub tvl: f64,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PoolAsset {
+    pub denom: String,
+    pub amount: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolPosition {
+    pub pool: String,
+    pub lp_amount: String,
+    pub assets: Vec<PoolAsset>,
+    pub usd: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletSummary {
+    pub address: String,
+    #[serde(rename = "hasLPd")]
+    pub has_lpd: bool,
+    pub has_traded: bool,
+    pub tvl: f64,
+}
+
+/// Liquidity positions of an address returned by the batched endpoint.
+#[derive(Debug, Deserialize)]
+pub struct WalletPositions {
+    pub address: String,
+    pub positions: Vec<PoolPosition>,
+}
+
+#[derive(Debug, Serialize)]
+struct WalletsRequest<'a> {
+    addresses: &'a [String],
+}
+
 #[derive(Debug)]
 pub struct AstrovaultClient {
     url: Url,
     svc: Mutex<BoxService<reqwest::Request, reqwest::Response, BoxError>>,
+    batch_size: Option<usize>,
 }
 
 impl AstrovaultClient {
@@ -35,19 +118,62 @@ impl AstrovaultClient {
             url,
             req_second: None,
             api_key: None,
+            batch_size: None,
         }
     }
 
+    /// Number of addresses per request when the batched endpoint is enabled.
+    pub fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+
     pub async fn stats(&self, address: &str) -> Result<WalletStats> {
-        self.request("/wallet/stats", address).await
+        self.get("/wallet/stats", address).await
     }
 
     pub async fn tvl(&self, address: &str) -> Result<WalletTvl> {
-        self.request("/wallet/tvl", address).await
+        self.get("/wallet/tvl", address).await
+    }
+
+    /// Liquidity positions of the address, one per pool.
+    pub async fn positions(&self, address: &str) -> Result<Vec<PoolPosition>> {
+        self.get("/wallet/positions", address).await
+    }
+
+    /// Fetches the stats and tvl for many addresses in a single request
+    /// using the batched endpoint.
+    #[tracing::instrument(skip_all, fields(count = addresses.len()))]
+    pub async fn wallets(&self, addresses: &[String]) -> Result<Vec<WalletSummary>> {
+        self.post("/wallets/batch", addresses).await
+    }
+
+    /// Fetches the liquidity positions for many addresses in a single request
+    /// using the batched endpoint.
+    #[tracing::instrument(skip_all, fields(count = addresses.len()))]
+    pub async fn wallets_positions(&self, addresses: &[String]) -> Result<Vec<WalletPositions>> {
+        self.post("/wallets/positions/batch", addresses).await
+    }
+
+    #[tracing::instrument(skip(self, addresses))]
+    async fn post<R>(&self, endpoint: &str, addresses: &[String]) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        let url = self.url.join(endpoint)?;
+        let body = serde_json::to_vec(&WalletsRequest { addresses })?;
+
+        let mut request = reqwest::Request::new(reqwest::Method::POST, url);
+        request.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        );
+        *request.body_mut() = Some(body.into());
+
+        self.request(request).await
     }
 
     #[tracing::instrument(skip(self))]
-    async fn request<R>(&self, endpoint: &str, address: &str) -> Result<R>
+    async fn get<R>(&self, endpoint: &str, address: &str) -> Result<R>
     where
         R: serde::de::DeserializeOwned,
     {
@@ -55,17 +181,50 @@ impl AstrovaultClient {
             .url
             .join(format!("{endpoint}?address={address}").as_str())?;
 
-        let mut svc = self.svc.lock().await;
-        let client = svc.ready().await.map_err(|err| anyhow!(err))?;
+        self.request(reqwest::Request::new(reqwest::Method::GET, url))
+            .await
+    }
 
-        tracing::debug!("executing request");
-        let request = reqwest::Request::new(reqwest::Method::GET, url);
-        let response = client.call(request).await.map_err(|err| anyhow!(err))?;
-        tracing::debug!(?response, "got response");
+    async fn request<R>(&self, request: reqwest::Request) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        let mut attempt = 1;
+        loop {
+            let retry = request
+                .try_clone()
+                .ok_or(anyhow!("request body can't be retried"))?;
+            let response = self.send(retry).await?;
 
-        let json = response.json::<R>().await?;
+            match AstrovaultError::from_status(response.status()) {
+                None => {
+                    let json = response.json::<R>().await?;
+                    return Ok(json);
+                }
+                Some(err) if err.is_transient() && attempt < MAX_ATTEMPTS => {
+                    tracing::warn!(%err, attempt, "request failed, retrying");
+                    tokio::time::sleep(RETRY_DELAY * attempt).await;
+                    attempt += 1;
+                }
+                Some(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    async fn send(&self, request: reqwest::Request) -> Result<reqwest::Response> {
+        // Only hold the lock while reserving a slot in the service,
+        // so requests from different tasks can run concurrently.
+        let response = {
+            let mut svc = self.svc.lock().await;
+            let client = svc.ready().await.map_err(|err| anyhow!(err))?;
+
+            tracing::debug!(?request, "executing request");
+            client.call(request)
+        };
+        let response = response.await.map_err(|err| anyhow!(err))?;
+        tracing::debug!(?response, "got response");
 
-        Ok(json)
+        Ok(response)
     }
 }
 
@@ -73,6 +232,7 @@ pub struct Builder {
     url: Url,
     req_second: Option<u64>,
     api_key: Option<String>,
+    batch_size: Option<usize>,
 }
 
 impl Builder {
@@ -86,6 +246,12 @@ impl Builder {
         self
     }
 
+    /// Enables the batched wallets endpoint, sending this many addresses per request.
+    pub fn batch_size(mut self, batch_size: Option<usize>) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
     pub async fn build(self) -> Result<AstrovaultClient> {
         let mut headers = header::HeaderMap::new();
 
@@ -111,6 +277,10 @@ impl Builder {
             .boxed()
             .into();
 
-        Ok(AstrovaultClient { url: self.url, svc })
+        Ok(AstrovaultClient {
+            url: self.url,
+            svc,
+            batch_size: self.batch_size,
+        })
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::time::Duration;
 
 use anyhow::{anyhow, Result};
@@ -8,6 +8,9 @@ use tower::util::BoxService;
 use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
 use url::Url;
 
+/// Maximum number of ids sent in a single `simple/price` request.
+const PRICE_BATCH_SIZE: usize = 50;
+
 #[derive(Copy, Clone, Debug, Deserialize)]
 pub struct CoinPrice {
     pub usd: f64,
@@ -15,11 +18,15 @@ pub struct CoinPrice {
 
 type PriceResponse = HashMap<String, CoinPrice>;
 
+/// CoinGecko client holding the prices for every known token.
+///
+/// Prices are fetched once when the client is built, so lookups
+/// during the export are plain reads without any locking.
 #[derive(Debug)]
 pub struct CoinGeckoClient {
     url: Url,
     svc: Mutex<BoxService<reqwest::Request, reqwest::Response, BoxError>>,
-    price_cache: Mutex<PriceResponse>,
+    prices: PriceResponse,
 }
 
 impl CoinGeckoClient {
@@ -30,50 +37,54 @@ impl CoinGeckoClient {
         Self {
             url,
             svc,
-            price_cache: HashMap::new().into(),
+            prices: HashMap::new(),
         }
     }
 
     pub fn builder(url: Url) -> Builder {
-        Builder { url }
+        Builder {
+            url,
+            ids: BTreeSet::new(),
+        }
     }
 
-    pub async fn price(&self, ids: Vec<&str>) -> Result<PriceResponse> {
-        tracing::debug!(?ids, "fetching coin prices");
+    /// Returns the prefetched prices for the given ids.
+    /// Ids without a known price are left out of the response.
+    pub fn price(&self, ids: Vec<&str>) -> PriceResponse {
+        tracing::debug!(?ids, "reading coin prices");
 
-        if ids.is_empty() {
-            return Ok(PriceResponse::default());
-        }
+        ids.into_iter()
+            .filter_map(|id| self.prices.get(id).map(|&price| (id.to_string(), price)))
+            .collect()
+    }
 
-        let mut cache = self.price_cache.lock().await;
+    #[tracing::instrument(skip_all)]
+    async fn prefetch(&mut self, ids: &BTreeSet<String>) -> Result<()> {
+        tracing::info!(count = ids.len(), "prefetching coin prices");
 
-        let query_ids: Vec<&str> = ids
-            .iter()
-            .filter(|&id| !cache.contains_key(*id))
-            .copied()
-            .collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        for chunk in ids.chunks(PRICE_BATCH_SIZE) {
+            tracing::debug!(ids = ?chunk, "fetching coin prices");
 
-        if !query_ids.is_empty() {
             let endpoint = format!(
                 "/api/v3/simple/price?ids={}&vs_currencies=usd&precision=full",
-                query_ids.as_slice().join(",")
+                chunk.join(",")
             );
             let response: PriceResponse = self.request(endpoint.as_str()).await?;
-            cache.extend(response.clone());
+            self.prices.extend(response);
         }
 
-        let prices = ids
+        let unknown: Vec<&str> = ids
             .into_iter()
-            .map(|id| {
-                cache
-                    .get(id)
-                    .as_deref()
-                    .map(|&price| (id.to_string(), price))
-                    .ok_or(anyhow!("missing price for {}", id))
-            })
-            .collect::<Result<PriceResponse>>()?;
-
-        Ok(prices)
+            .filter(|id| !self.prices.contains_key(*id))
+            .collect();
+        if !unknown.is_empty() {
+            tracing::warn!(?unknown, "CoinGecko has no price for some token ids");
+        }
+
+        tracing::info!(count = self.prices.len(), "coin prices prefetched");
+
+        Ok(())
     }
 
     #[tracing::instrument(skip(self))]
@@ -99,9 +110,19 @@ impl CoinGeckoClient {
 
 pub struct Builder {
     url: Url,
+    ids: BTreeSet<String>,
 }
 
 impl Builder {
+    pub fn prefetch<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ids.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
     pub async fn build(self) -> Result<CoinGeckoClient> {
         let client = reqwest::Client::builder()
             .timeout(Duration::from_secs(30))
@@ -115,6 +136,9 @@ impl Builder {
             .boxed()
             .into();
 
-        Ok(CoinGeckoClient::new(self.url, svc))
+        let mut coingecko = CoinGeckoClient::new(self.url, svc);
+        coingecko.prefetch(&self.ids).await?;
+
+        Ok(coingecko)
     }
 }
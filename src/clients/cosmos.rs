@@ -4,32 +4,49 @@ use anyhow::Result;
 use url::Url;
 
 use super::rpc::RpcClient;
+use crate::block::{Block, BlockId};
 
 mod bank;
 mod cosmwasm;
+mod gov;
+mod ibc;
+mod rewards;
 mod staking;
+mod tx;
+
+pub use gov::{Proposal, ProposalStatus, VoteOption};
 
 #[derive(Debug)]
 pub struct CosmosClient {
+    pub block: Block,
     pub bank: bank::QueryClient,
     pub staking: staking::QueryClient,
     pub cosmwasm: cosmwasm::QueryClient,
+    pub gov: gov::QueryClient,
+    pub ibc: ibc::QueryClient,
+    pub rewards: rewards::QueryClient,
+    pub tx: tx::QueryClient,
 }
 
 impl CosmosClient {
-    pub async fn new(url: Url, req_second: Option<u64>, height: Option<u64>) -> Result<Self> {
+    pub async fn new(url: Url, req_second: Option<u64>, block: Option<BlockId>) -> Result<Self> {
         let rpc = Arc::new(
             RpcClient::builder(url)
                 .req_second(req_second)
-                .height(height)
+                .block(block)
                 .build()
                 .await?,
         );
 
         let client = CosmosClient {
+            block: rpc.block().clone(),
             bank: bank::QueryClient::new(rpc.clone()),
             staking: staking::QueryClient::new(rpc.clone()),
             cosmwasm: cosmwasm::QueryClient::new(rpc.clone()),
+            gov: gov::QueryClient::new(rpc.clone()),
+            ibc: ibc::QueryClient::new(rpc.clone()),
+            rewards: rewards::QueryClient::new(rpc.clone()),
+            tx: tx::QueryClient::new(rpc.clone()),
         };
 
         Ok(client)
@@ -1,6 +1,7 @@
 use std::{any::type_name, sync::Arc};
 
 use anyhow::*;
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::{PageRequest, PageResponse};
 use cosmos_sdk_proto::cosmwasm::wasm::v1::{
     QuerySmartContractStateRequest, QuerySmartContractStateResponse,
 };
@@ -8,6 +9,24 @@ use serde::{de::DeserializeOwned, Serialize};
 
 use super::super::rpc::RpcClient;
 
+// `ContractsByCreator` was added after the wasmd version of `cosmos-sdk-proto`.
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryContractsByCreatorRequest {
+    #[prost(string, tag = "1")]
+    creator_address: String,
+    #[prost(message, optional, tag = "2")]
+    pagination: Option<PageRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryContractsByCreatorResponse {
+    #[prost(string, repeated, tag = "1")]
+    contract_addresses: Vec<String>,
+    #[prost(message, optional, tag = "2")]
+    pagination: Option<PageResponse>,
+}
+
 #[derive(Debug, Clone)]
 pub struct QueryClient {
     rpc: Arc<RpcClient>,
@@ -42,4 +61,33 @@ impl QueryClient {
 
         Ok(data)
     }
+
+    /// Addresses of the contracts instantiated by the creator.
+    #[tracing::instrument(skip(self))]
+    pub async fn contracts_by_creator(&self, creator_address: String) -> Result<Vec<String>> {
+        let mut contracts = Vec::new();
+        let mut key = Vec::new();
+        loop {
+            let pagination = PageRequest {
+                key,
+                limit: 1000,
+                ..Default::default()
+            };
+            let request = QueryContractsByCreatorRequest {
+                creator_address: creator_address.clone(),
+                pagination: Some(pagination),
+            };
+
+            let response: QueryContractsByCreatorResponse = self
+                .rpc
+                .request("cosmwasm.wasm.v1.Query", "ContractsByCreator", request)
+                .await?;
+            contracts.extend(response.contract_addresses);
+
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => key = page.next_key,
+                _ => return Ok(contracts),
+            }
+        }
+    }
 }
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::gov::{v1, v1beta1};
+use cosmos_sdk_proto::Timestamp;
+
+pub use cosmos_sdk_proto::cosmos::gov::v1::{ProposalStatus, VoteOption};
+
+use super::super::rpc::{QueryError, RpcClient};
+
+/// A governance proposal, from either version of the module.
+#[derive(Debug, Clone)]
+pub struct Proposal {
+    pub id: u64,
+    pub status: ProposalStatus,
+    pub voting_start_time: Option<DateTime<Utc>>,
+    pub voting_end_time: Option<DateTime<Utc>>,
+    /// Whether the proposal comes from the `v1beta1` module, on chains without `v1`.
+    pub legacy: bool,
+}
+
+impl From<v1::Proposal> for Proposal {
+    fn from(proposal: v1::Proposal) -> Self {
+        Self {
+            id: proposal.id,
+            status: proposal.status.try_into().unwrap_or_default(),
+            voting_start_time: proposal.voting_start_time.and_then(to_datetime),
+            voting_end_time: proposal.voting_end_time.and_then(to_datetime),
+            legacy: false,
+        }
+    }
+}
+
+impl From<v1beta1::Proposal> for Proposal {
+    fn from(proposal: v1beta1::Proposal) -> Self {
+        Self {
+            id: proposal.proposal_id,
+            status: proposal.status.try_into().unwrap_or_default(),
+            voting_start_time: proposal.voting_start_time.and_then(to_datetime),
+            voting_end_time: proposal.voting_end_time.and_then(to_datetime),
+            legacy: true,
+        }
+    }
+}
+
+/// Options of a vote, with their weights as decimal strings.
+#[derive(Debug, Clone)]
+pub struct Vote {
+    pub proposal_id: u64,
+    pub options: Vec<(VoteOption, String)>,
+}
+
+impl From<v1::Vote> for Vote {
+    fn from(vote: v1::Vote) -> Self {
+        let options = vote
+            .options
+            .into_iter()
+            .map(|option| (option.option.try_into().unwrap_or_default(), option.weight))
+            .collect();
+
+        Self {
+            proposal_id: vote.proposal_id,
+            options,
+        }
+    }
+}
+
+impl From<v1beta1::Vote> for Vote {
+    #[allow(deprecated)]
+    fn from(vote: v1beta1::Vote) -> Self {
+        let mut options: Vec<(VoteOption, String)> = vote
+            .options
+            .into_iter()
+            .map(|option| (option.option.try_into().unwrap_or_default(), option.weight))
+            .collect();
+
+        // votes cast before weighted voting only have the deprecated option
+        if options.is_empty() {
+            options.push((vote.option.try_into().unwrap_or_default(), "1".to_string()));
+        }
+
+        Self {
+            proposal_id: vote.proposal_id,
+            options,
+        }
+    }
+}
+
+fn to_datetime(timestamp: Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp.seconds, timestamp.nanos.try_into().ok()?)
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryClient {
+    rpc: Arc<RpcClient>,
+}
+
+impl QueryClient {
+    pub fn new(rpc: Arc<RpcClient>) -> Self {
+        Self { rpc }
+    }
+
+    /// Every proposal, queried from the `v1beta1` module on chains without `v1`.
+    #[tracing::instrument(skip(self))]
+    pub async fn proposals(&self) -> anyhow::Result<Vec<Proposal>> {
+        match self.proposals_v1().await {
+            Ok(proposals) => return Ok(proposals.into_iter().map(Proposal::from).collect()),
+            Err(err) if !is_unknown_query(&err) => return Err(err),
+            Err(_) => tracing::debug!("no v1 gov module, querying v1beta1"),
+        }
+
+        let proposals = self
+            .proposals_v1beta1()
+            .await?
+            .into_iter()
+            .map(Proposal::from)
+            .collect();
+
+        Ok(proposals)
+    }
+
+    /// Vote of an address on a proposal still in its voting period, queried from
+    /// the module the proposal comes from, `None` when the address did not vote.
+    ///
+    /// Votes are removed from the state once the proposal is tallied.
+    #[tracing::instrument(skip(self, proposal), fields(proposal_id = proposal.id))]
+    pub async fn vote(&self, proposal: &Proposal, voter: &str) -> anyhow::Result<Option<Vote>> {
+        let vote = if proposal.legacy {
+            let request = v1beta1::QueryVoteRequest {
+                proposal_id: proposal.id,
+                voter: voter.to_string(),
+            };
+            self.rpc
+                .request("cosmos.gov.v1beta1.Query", "Vote", request)
+                .await
+                .map(|response: v1beta1::QueryVoteResponse| response.vote.map(Vote::from))
+        } else {
+            let request = v1::QueryVoteRequest {
+                proposal_id: proposal.id,
+                voter: voter.to_string(),
+            };
+            self.rpc
+                .request("cosmos.gov.v1.Query", "Vote", request)
+                .await
+                .map(|response: v1::QueryVoteResponse| response.vote.map(Vote::from))
+        };
+
+        match vote {
+            Err(err) if is_vote_not_found(&err) => Ok(None),
+            vote => vote,
+        }
+    }
+
+    async fn proposals_v1(&self) -> anyhow::Result<Vec<v1::Proposal>> {
+        let mut proposals = Vec::new();
+        let mut key = Vec::new();
+        loop {
+            let request = v1::QueryProposalsRequest {
+                pagination: Some(pagination(key)),
+                ..Default::default()
+            };
+            let response: v1::QueryProposalsResponse = self
+                .rpc
+                .request("cosmos.gov.v1.Query", "Proposals", request)
+                .await?;
+            proposals.extend(response.proposals);
+
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => key = page.next_key,
+                _ => return Ok(proposals),
+            }
+        }
+    }
+
+    async fn proposals_v1beta1(&self) -> anyhow::Result<Vec<v1beta1::Proposal>> {
+        let mut proposals = Vec::new();
+        let mut key = Vec::new();
+        loop {
+            let request = v1beta1::QueryProposalsRequest {
+                pagination: Some(pagination(key)),
+                ..Default::default()
+            };
+            let response: v1beta1::QueryProposalsResponse = self
+                .rpc
+                .request("cosmos.gov.v1beta1.Query", "Proposals", request)
+                .await?;
+            proposals.extend(response.proposals);
+
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => key = page.next_key,
+                _ => return Ok(proposals),
+            }
+        }
+    }
+}
+
+fn is_unknown_query(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<QueryError>()
+        .is_some_and(QueryError::is_unknown_query)
+}
+
+/// Whether the vote query failed because the address did not vote, which the gov
+/// module answers with an `InvalidArgument` error saying the voter is not found.
+fn is_vote_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<QueryError>().is_some_and(|err| {
+        err.is_not_found() || (err.is_invalid_request() && err.log.contains("not found"))
+    })
+}
+
+/// A page of proposals starting at the `next_key` of the previous one.
+fn pagination(key: Vec<u8>) -> PageRequest {
+    PageRequest {
+        key,
+        limit: 1000,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use cosmos_sdk_proto::ibc::core::channel::v1::{
+    QueryChannelClientStateRequest, QueryChannelClientStateResponse,
+};
+use cosmos_sdk_proto::ibc::lightclients::tendermint::v1::ClientState;
+use cosmos_sdk_proto::prost::Message;
+
+use super::super::rpc::RpcClient;
+
+const TENDERMINT_CLIENT_STATE: &str = "/ibc.lightclients.tendermint.v1.ClientState";
+
+#[derive(Debug, Clone)]
+pub struct QueryClient {
+    rpc: Arc<RpcClient>,
+}
+
+impl QueryClient {
+    pub fn new(rpc: Arc<RpcClient>) -> Self {
+        Self { rpc }
+    }
+
+    /// Id of the chain at the other end of a channel, `None` when its light client
+    /// is not a Tendermint one.
+    #[tracing::instrument(skip(self))]
+    pub async fn counterparty_chain_id(
+        &self,
+        port_id: &str,
+        channel_id: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let request = QueryChannelClientStateRequest {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+        };
+
+        let response: QueryChannelClientStateResponse = self
+            .rpc
+            .request("ibc.core.channel.v1.Query", "ChannelClientState", request)
+            .await?;
+
+        let client_state = response
+            .identified_client_state
+            .and_then(|state| state.client_state)
+            .filter(|state| state.type_url == TENDERMINT_CLIENT_STATE);
+
+        let chain_id = match client_state {
+            Some(state) => Some(ClientState::decode(&*state.value)?.chain_id),
+            None => None,
+        };
+
+        Ok(chain_id)
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::{PageRequest, PageResponse};
+use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+use cosmos_sdk_proto::Timestamp;
+
+use super::super::rpc::{QueryError, RpcClient};
+
+// Messages of the `archway.rewards.v1` module, which is not part of `cosmos-sdk-proto`.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ContractMetadata {
+    #[prost(string, tag = "1")]
+    pub contract_address: String,
+    #[prost(string, tag = "2")]
+    pub owner_address: String,
+    #[prost(string, tag = "3")]
+    pub rewards_address: String,
+    #[prost(bool, tag = "4")]
+    pub withdraw_to_wallet: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RewardsRecord {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    #[prost(string, tag = "2")]
+    pub rewards_address: String,
+    #[prost(message, repeated, tag = "3")]
+    pub rewards: Vec<Coin>,
+    #[prost(int64, tag = "4")]
+    pub calculated_height: i64,
+    #[prost(message, optional, tag = "5")]
+    pub calculated_time: Option<Timestamp>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryContractMetadataRequest {
+    #[prost(string, tag = "1")]
+    contract_address: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryContractMetadataResponse {
+    #[prost(message, optional, tag = "1")]
+    metadata: Option<ContractMetadata>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryRewardsRecordsRequest {
+    #[prost(string, tag = "1")]
+    rewards_address: String,
+    #[prost(message, optional, tag = "2")]
+    pagination: Option<PageRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryRewardsRecordsResponse {
+    #[prost(message, repeated, tag = "1")]
+    records: Vec<RewardsRecord>,
+    #[prost(message, optional, tag = "2")]
+    pagination: Option<PageResponse>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryOutstandingRewardsRequest {
+    #[prost(string, tag = "1")]
+    rewards_address: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryOutstandingRewardsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub total_rewards: Vec<Coin>,
+    #[prost(uint64, tag = "2")]
+    pub records_num: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryClient {
+    rpc: Arc<RpcClient>,
+}
+
+impl QueryClient {
+    pub fn new(rpc: Arc<RpcClient>) -> Self {
+        Self { rpc }
+    }
+
+    /// Rewards metadata of a contract, `None` when it was never set.
+    #[tracing::instrument(skip(self))]
+    pub async fn contract_metadata(
+        &self,
+        contract_address: String,
+    ) -> anyhow::Result<Option<ContractMetadata>> {
+        let request = QueryContractMetadataRequest { contract_address };
+        let response: anyhow::Result<QueryContractMetadataResponse> = self
+            .rpc
+            .request("archway.rewards.v1.Query", "ContractMetadata", request)
+            .await;
+
+        // the module answers `NotFound` for contracts without metadata
+        match response {
+            Ok(response) => Ok(response.metadata),
+            Err(err)
+                if err
+                    .downcast_ref::<QueryError>()
+                    .is_some_and(QueryError::is_not_found) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Rewards records not yet withdrawn by the rewards address.
+    #[tracing::instrument(skip(self))]
+    pub async fn rewards_records(
+        &self,
+        rewards_address: String,
+    ) -> anyhow::Result<Vec<RewardsRecord>> {
+        let mut records = Vec::new();
+        let mut key = Vec::new();
+        loop {
+            let pagination = PageRequest {
+                key,
+                limit: 1000,
+                ..Default::default()
+            };
+            let request = QueryRewardsRecordsRequest {
+                rewards_address: rewards_address.clone(),
+                pagination: Some(pagination),
+            };
+            let response: QueryRewardsRecordsResponse = self
+                .rpc
+                .request("archway.rewards.v1.Query", "RewardsRecords", request)
+                .await?;
+            records.extend(response.records);
+
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => key = page.next_key,
+                _ => return Ok(records),
+            }
+        }
+    }
+
+    /// Total of the rewards not yet withdrawn by the rewards address.
+    #[tracing::instrument(skip(self))]
+    pub async fn outstanding_rewards(
+        &self,
+        rewards_address: String,
+    ) -> anyhow::Result<QueryOutstandingRewardsResponse> {
+        let request = QueryOutstandingRewardsRequest { rewards_address };
+
+        self.rpc
+            .request("archway.rewards.v1.Query", "OutstandingRewards", request)
+            .await
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use futures::stream::BoxStream;
+use tendermint_rpc::endpoint::tx;
+use tendermint_rpc::query::Query;
+
+use super::super::rpc::RpcClient;
+
+#[derive(Debug, Clone)]
+pub struct QueryClient {
+    rpc: Arc<RpcClient>,
+}
+
+const MSG_SEND: &str = "/cosmos.bank.v1beta1.MsgSend";
+const MSG_TRANSFER: &str = "/ibc.applications.transfer.v1.MsgTransfer";
+
+/// A bank send of tokens into an address.
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub sender: String,
+    pub height: u64,
+}
+
+impl QueryClient {
+    pub fn new(rpc: Arc<RpcClient>) -> Self {
+        Self { rpc }
+    }
+
+    /// Oldest transaction matching the query.
+    #[tracing::instrument(skip(self))]
+    pub async fn first(&self, query: Query) -> anyhow::Result<Option<tx::Response>> {
+        let response = self.rpc.tx_search(query, 1, 1).await?;
+        Ok(response.txs.into_iter().next())
+    }
+
+    /// Transactions with a message sent by the address, from a height up to the pinned block.
+    pub fn sent(
+        &self,
+        sender: &str,
+        from_height: Option<u64>,
+    ) -> BoxStream<'_, anyhow::Result<tx::Response>> {
+        let query = Query::eq("message.sender", sender);
+        self.rpc.tx_search_all(query, from_height)
+    }
+
+    /// Transactions transferring tokens to the address, from a height up to the pinned block.
+    pub fn received(
+        &self,
+        recipient: &str,
+        from_height: Option<u64>,
+    ) -> BoxStream<'_, anyhow::Result<tx::Response>> {
+        let query = Query::eq("transfer.recipient", recipient);
+        self.rpc.tx_search_all(query, from_height)
+    }
+
+    /// Transactions with an IBC transfer sent by the address.
+    pub fn ibc_sent(
+        &self,
+        sender: &str,
+        from_height: Option<u64>,
+    ) -> BoxStream<'_, anyhow::Result<tx::Response>> {
+        let query = Query::eq("message.sender", sender).and_eq("message.action", MSG_TRANSFER);
+        self.rpc.tx_search_all(query, from_height)
+    }
+
+    /// Transactions with an IBC transfer received by the address.
+    pub fn ibc_received(
+        &self,
+        receiver: &str,
+        from_height: Option<u64>,
+    ) -> BoxStream<'_, anyhow::Result<tx::Response>> {
+        let query = Query::eq("fungible_token_packet.receiver", receiver);
+        self.rpc.tx_search_all(query, from_height)
+    }
+
+    /// Transactions with a governance vote cast by the address.
+    pub fn votes(
+        &self,
+        voter: &str,
+        from_height: Option<u64>,
+    ) -> BoxStream<'_, anyhow::Result<tx::Response>> {
+        let query = Query::eq("message.sender", voter).and_exists("proposal_vote.proposal_id");
+        self.rpc.tx_search_all(query, from_height)
+    }
+
+    /// Transactions withdrawing the contract rewards sent to the address.
+    ///
+    /// Typed events quote their string attributes as JSON.
+    pub fn rewards_withdrawals(
+        &self,
+        rewards_address: &str,
+    ) -> BoxStream<'_, anyhow::Result<tx::Response>> {
+        let query = Query::eq(
+            "archway.rewards.v1.RewardsWithdrawEvent.rewards_address",
+            format!("\"{}\"", rewards_address),
+        );
+        self.rpc.tx_search_all(query, None)
+    }
+
+    /// Transactions setting a contract rewards metadata that mentions the address,
+    /// as its owner or as its rewards address.
+    pub fn contract_metadata_sets(
+        &self,
+        address: &str,
+    ) -> BoxStream<'_, anyhow::Result<tx::Response>> {
+        let query = Query::contains(
+            "archway.rewards.v1.ContractMetadataSetEvent.metadata",
+            address,
+        );
+        self.rpc.tx_search_all(query, None)
+    }
+
+    /// First `MsgSend` of tokens received by the address.
+    #[tracing::instrument(skip(self))]
+    pub async fn first_send(&self, recipient: &str) -> anyhow::Result<Option<Transfer>> {
+        let query = Query::eq("message.action", MSG_SEND).and_eq("transfer.recipient", recipient);
+        let Some(tx) = self.first(query).await? else {
+            return Ok(None);
+        };
+
+        let sender = tx
+            .tx_result
+            .events
+            .iter()
+            .filter(|event| event.kind == "transfer")
+            .find(|event| {
+                event
+                    .attributes
+                    .iter()
+                    .any(|attr| attr.key == "recipient" && attr.value == recipient)
+            })
+            .and_then(|event| event.attributes.iter().find(|attr| attr.key == "sender"))
+            .map(|attr| attr.value.clone());
+
+        Ok(sender.map(|sender| Transfer {
+            sender,
+            height: tx.height.value(),
+        }))
+    }
+}
@@ -3,6 +3,6 @@ mod coingecko;
 mod cosmos;
 mod rpc;
 
-pub use astrovault::AstrovaultClient;
-pub use cosmos::CosmosClient;
+pub use astrovault::{AstrovaultClient, AstrovaultError, PoolPosition, WalletSummary};
 pub use coingecko::CoinGeckoClient;
+pub use cosmos::{CosmosClient, Proposal, ProposalStatus, VoteOption};
@@ -2,13 +2,16 @@ use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
 use cosmos_sdk_proto::prost::Message;
 use futures::prelude::future::{BoxFuture, FutureExt};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use tendermint::block::Height;
-use tendermint_rpc::endpoint::abci_query;
+use tendermint_rpc::endpoint::{abci_query, tx, tx_search};
 use tendermint_rpc::{
-    client::CompatMode, Client, Error as TmRpcError, HttpClient, Url as TmRpcUrl,
+    client::CompatMode, query::Query, Client, Error as TmRpcError, HttpClient, Order,
+    Url as TmRpcUrl,
 };
 use tokio::sync::Mutex;
 use tower::limit::RateLimitLayer;
@@ -16,25 +19,99 @@ use tower::util::BoxService;
 use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
 use url::Url;
 
-use crate::block::Block;
+use crate::block::{Block, BlockId};
+
+/// Maximum number of transactions per page allowed by the RPC nodes.
+const TX_PER_PAGE: u8 = 100;
+
+/// Codespace of the Cosmos SDK errors.
+const SDK_CODESPACE: &str = "sdk";
+/// Code of the SDK error returned for a query path no module serves.
+const UNKNOWN_REQUEST: u32 = 6;
+/// Code of the SDK error returned for a gRPC query answered with `InvalidArgument`.
+const INVALID_REQUEST: u32 = 18;
+/// Code of the SDK error returned for a gRPC query answered with `NotFound`.
+const KEY_NOT_FOUND: u32 = 38;
 
 /// A RPC client wrapper that queries ProtoBuf
 /// encoded message for a single block height.
 #[derive(Debug)]
 pub struct RpcClient {
-    svc: Mutex<BoxService<abci_query::Request, abci_query::Response, BoxError>>,
+    svc: Mutex<BoxService<RpcRequest, RpcResponse, BoxError>>,
     block: Block,
 }
 
+/// A page of a transaction search, oldest first.
+#[derive(Debug, Clone)]
+pub struct TxSearch {
+    pub query: Query,
+    pub page: u32,
+    pub per_page: u8,
+}
+
+/// Requests sent through the same service, so they share the rate limit of the endpoint.
+#[derive(Debug, Clone)]
+pub enum RpcRequest {
+    AbciQuery(abci_query::Request),
+    TxSearch(TxSearch),
+}
+
+#[derive(Debug)]
+pub enum RpcResponse {
+    AbciQuery(abci_query::Response),
+    TxSearch(tx_search::Response),
+}
+
+/// Error of an ABCI query answered with a non-zero code.
+#[derive(Debug)]
+pub struct QueryError {
+    pub codespace: String,
+    pub code: u32,
+    pub log: String,
+}
+
+impl QueryError {
+    /// Whether the queried item does not exist.
+    pub fn is_not_found(&self) -> bool {
+        self.codespace == SDK_CODESPACE && self.code == KEY_NOT_FOUND
+    }
+
+    /// Whether the request was rejected as invalid.
+    pub fn is_invalid_request(&self) -> bool {
+        self.codespace == SDK_CODESPACE && self.code == INVALID_REQUEST
+    }
+
+    /// Whether the query path is not served, such as a module missing from the chain.
+    pub fn is_unknown_query(&self) -> bool {
+        self.codespace == SDK_CODESPACE && self.code == UNKNOWN_REQUEST
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "query failed with code {} in codespace {}: {}",
+            self.code, self.codespace, self.log
+        )
+    }
+}
+
+impl std::error::Error for QueryError {}
+
 impl RpcClient {
     pub fn builder(url: Url) -> Builder {
         Builder {
             url,
             req_second: None,
-            height: None,
+            block: None,
         }
     }
 
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+
     #[tracing::instrument(fields(height = self.block.height.value()), skip(self, data))]
     pub async fn request<T, R>(&self, service: &str, method: &str, data: T) -> Result<R>
     where
@@ -51,21 +128,105 @@ impl RpcClient {
             false,
         );
 
-        let mut svc = self.svc.lock().await;
-        let client = svc.ready().await.map_err(|err| anyhow!(err))?;
-        let response = client.call(request).await.map_err(|err| anyhow!(err))?;
+        let RpcResponse::AbciQuery(response) = self.call(RpcRequest::AbciQuery(request)).await?
+        else {
+            bail!("unexpected response to the abci query");
+        };
+
+        let response = response.response;
+        if response.code.is_err() {
+            return Err(QueryError {
+                codespace: response.codespace,
+                code: response.code.value(),
+                log: response.log,
+            }
+            .into());
+        }
 
-        let response: R = R::decode(&*response.response.value)?;
+        let response: R = R::decode(&*response.value)?;
         tracing::debug!(?response, "response");
 
         Ok(response)
     }
+
+    /// Searches the transactions matching the query up to the pinned block, oldest first.
+    #[tracing::instrument(fields(height = self.block.height.value()), skip(self))]
+    pub async fn tx_search(
+        &self,
+        query: Query,
+        page: u32,
+        per_page: u8,
+    ) -> Result<tx_search::Response> {
+        let request = TxSearch {
+            query: query.and_lte("tx.height", self.block.height.value()),
+            page,
+            per_page,
+        };
+
+        let RpcResponse::TxSearch(response) = self.call(RpcRequest::TxSearch(request)).await?
+        else {
+            bail!("unexpected response to the transaction search");
+        };
+        tracing::debug!(total = response.total_count, "transactions found");
+
+        Ok(response)
+    }
+
+    /// Searches every transaction matching the query from a height
+    /// up to the pinned block, oldest first.
+    ///
+    /// The pages are fetched as the stream is consumed, so the transactions
+    /// of long histories are never all kept in memory.
+    pub fn tx_search_all(
+        &self,
+        query: Query,
+        from_height: Option<u64>,
+    ) -> BoxStream<'_, Result<tx::Response>> {
+        let query = match from_height {
+            Some(height) => query.and_gte("tx.height", height),
+            None => query,
+        };
+
+        // the next page to fetch, and the number of transactions already fetched
+        stream::try_unfold(Some((1, 0)), move |state| {
+            let query = query.clone();
+            async move {
+                let Some((page, fetched)) = state else {
+                    return anyhow::Ok(None);
+                };
+
+                let response = self.tx_search(query, page, TX_PER_PAGE).await?;
+                let fetched = fetched + response.txs.len();
+                let done = response.txs.len() < TX_PER_PAGE as usize
+                    || fetched >= response.total_count as usize;
+                let next = (!done).then_some((page + 1, fetched));
+
+                Ok(Some((response.txs, next)))
+            }
+        })
+        .map_ok(|txs| stream::iter(txs.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Sends a request once the rate limit of the endpoint allows it.
+    async fn call(&self, request: RpcRequest) -> Result<RpcResponse> {
+        // only holds the lock while reserving a slot in the service,
+        // so requests from different tasks can run concurrently
+        let response = {
+            let mut svc = self.svc.lock().await;
+            let client = svc.ready().await.map_err(|err| anyhow!(err))?;
+            client.call(request)
+        };
+
+        response.await.map_err(|err| anyhow!(err))
+    }
 }
 
 pub struct Builder {
     url: Url,
     req_second: Option<u64>,
-    height: Option<u64>,
+    block: Option<BlockId>,
 }
 
 impl Builder {
@@ -74,8 +235,8 @@ impl Builder {
         self
     }
 
-    pub fn height(mut self, height: Option<u64>) -> Self {
-        self.height = height;
+    pub fn block(mut self, block: Option<BlockId>) -> Self {
+        self.block = block;
         self
     }
 
@@ -88,27 +249,36 @@ impl Builder {
         let block = self.get_block(&client).await?;
         tracing::info!(block = ?block, "creating rpc client for block");
 
-        let svc = ServiceBuilder::new()
+        let client = HttpClientWrapper::new(client);
+        let svc = self.service(client).into();
+
+        Ok(RpcClient { svc, block })
+    }
+
+    /// Wraps the client with the buffer, concurrency and rate limits of the endpoint.
+    fn service(&self, client: HttpClientWrapper) -> BoxService<RpcRequest, RpcResponse, BoxError> {
+        ServiceBuilder::new()
             .buffer(100)
             .concurrency_limit(50)
             .option_layer(
                 self.req_second
                     .map(|num| RateLimitLayer::new(num, Duration::from_secs(1))),
             )
-            .service(HttpClientWrapper::new(client))
+            .service(client)
             .boxed()
-            .into();
-
-        Ok(RpcClient { svc, block })
     }
 
     async fn get_block(&self, client: &HttpClient) -> Result<Block> {
-        let block = match self.height {
-            Some(height) => {
+        let block = match self.block {
+            Some(BlockId::Height(height)) => {
                 tracing::debug!(%height, "using provided block height");
                 let height: Height = height.try_into()?;
                 client.block(height).await?
             }
+            Some(BlockId::Time(time)) => {
+                tracing::debug!(%time, "searching the block for the provided time");
+                return find_block(client, time).await;
+            }
             None => {
                 tracing::debug!("querying the chain for the latest block height");
                 client.latest_block().await?
@@ -119,6 +289,60 @@ impl Builder {
     }
 }
 
+/// Binary searches the block headers for the last block at or before the given time.
+async fn find_block(client: &HttpClient, time: DateTime<Utc>) -> Result<Block> {
+    let latest: Block = client
+        .latest_commit()
+        .await?
+        .signed_header
+        .header
+        .try_into()?;
+    if latest.time <= time {
+        tracing::info!(
+            height = %latest.height,
+            time = %latest.time,
+            "found block for {}, the latest one",
+            time
+        );
+        return Ok(latest);
+    }
+
+    let earliest = client.status().await?.sync_info.earliest_block_height;
+    let mut found = header(client, earliest.value()).await?;
+    if found.time > time {
+        bail!(
+            "{} is before the earliest block available, {} at {}",
+            time,
+            found.height,
+            found.time
+        );
+    }
+
+    // `found` is always at or before the time, and `high` the highest candidate
+    let mut high = latest.height.value() - 1;
+    while found.height.value() < high {
+        let low = found.height.value();
+        let mid = low + (high - low).div_ceil(2);
+
+        let block = header(client, mid).await?;
+        if block.time <= time {
+            found = block;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    tracing::info!(height = %found.height, time = %found.time, "found block for {}", time);
+
+    Ok(found)
+}
+
+async fn header(client: &HttpClient, height: u64) -> Result<Block> {
+    let height: Height = height.try_into()?;
+    let response = client.commit(height).await?;
+    response.signed_header.header.try_into()
+}
+
 #[derive(Debug, Clone)]
 pub struct HttpClientWrapper {
     client: Arc<HttpClient>,
@@ -132,14 +356,23 @@ impl HttpClientWrapper {
     }
 }
 
-impl Service<abci_query::Request> for HttpClientWrapper {
-    type Response = abci_query::Response;
+impl Service<RpcRequest> for HttpClientWrapper {
+    type Response = RpcResponse;
     type Error = TmRpcError;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    fn call(&mut self, req: abci_query::Request) -> Self::Future {
+    fn call(&mut self, req: RpcRequest) -> Self::Future {
         let client = self.client.clone();
-        async move { client.perform(req).await }.boxed()
+        async move {
+            match req {
+                RpcRequest::AbciQuery(req) => client.perform(req).await.map(RpcResponse::AbciQuery),
+                RpcRequest::TxSearch(req) => client
+                    .tx_search(req.query, false, req.page, req.per_page, Order::Ascending)
+                    .await
+                    .map(RpcResponse::TxSearch),
+            }
+        }
+        .boxed()
     }
 
     fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
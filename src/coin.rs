@@ -3,8 +3,9 @@ use std::str::FromStr;
 use anyhow::{anyhow, Result};
 use bigdecimal::{BigDecimal, ToPrimitive};
 use num::BigUint;
+use serde::Serialize;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Coin {
     pub denom: String,
     pub amount: BigDecimal,
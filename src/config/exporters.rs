@@ -0,0 +1,195 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+
+/// Exporters defined in the `--exporters` config file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct CustomExporters {
+    #[serde(default)]
+    pub cw20: Vec<Cw20Exporter>,
+    #[serde(default)]
+    pub cw721: Vec<Cw721Exporter>,
+    #[serde(default)]
+    pub smart_query: Vec<SmartQueryExporter>,
+}
+
+/// CW20 token whose balance is exported per address.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Cw20Exporter {
+    /// Name of the token in the CSV files.
+    pub name: String,
+    pub contract: String,
+    /// Key of the `ecosystem` activity ranking the balance.
+    pub activity: String,
+}
+
+/// CW721 collection whose tokens are counted per address.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Cw721Exporter {
+    /// Name of the collection in the CSV files.
+    pub name: String,
+    pub contract: String,
+    /// Key of the `ecosystem` activity ranking the number of tokens.
+    pub activity: String,
+    /// Also exports the traits of the tokens, with a query per token.
+    #[serde(default)]
+    pub traits: bool,
+}
+
+/// Contract smart query whose result is exported per address.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SmartQueryExporter {
+    /// Name of the query in the CSV files.
+    pub name: String,
+    pub contract: String,
+    /// JSON query message, where `{address}` is replaced by the participant address.
+    pub query: String,
+    /// Path of the score in the query response.
+    pub selector: Selector,
+    /// Decimals of the score, which is divided by `10^decimals`.
+    #[serde(default)]
+    pub decimals: u8,
+    /// Key of the `ecosystem` activity ranking the score.
+    pub activity: String,
+}
+
+/// A step of a selector path.
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Field(String),
+    Index(usize),
+}
+
+/// JSONPath-style path of a value in a JSON document, such as `$.balance`
+/// or `$.positions[0].amount`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Selector {
+    path: String,
+    steps: Vec<Step>,
+}
+
+impl Selector {
+    /// Value at the path, `None` when missing.
+    pub fn select<'a>(&self, value: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+        self.steps.iter().try_fold(value, |value, step| match step {
+            Step::Field(field) => value.get(field),
+            Step::Index(index) => value.get(index),
+        })
+    }
+}
+
+impl std::fmt::Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.path)
+    }
+}
+
+impl TryFrom<String> for Selector {
+    type Error = anyhow::Error;
+
+    fn try_from(path: String) -> anyhow::Result<Self> {
+        let invalid = || anyhow!("invalid selector: {}", path);
+
+        let mut steps = Vec::new();
+        let rest = path.strip_prefix('$').unwrap_or(&path);
+        for part in rest.split('.').filter(|part| !part.is_empty()) {
+            let (field, indexes) = match part.find('[') {
+                Some(i) => part.split_at(i),
+                None => (part, ""),
+            };
+            if !field.is_empty() {
+                steps.push(Step::Field(field.to_string()));
+            }
+
+            let mut indexes = indexes;
+            while !indexes.is_empty() {
+                let (index, next) = indexes
+                    .strip_prefix('[')
+                    .and_then(|index| index.split_once(']'))
+                    .ok_or_else(invalid)?;
+                steps.push(Step::Index(index.parse().map_err(|_| invalid())?));
+                indexes = next;
+            }
+        }
+
+        if steps.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self { path, steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn selector(path: &str) -> anyhow::Result<Selector> {
+        Selector::try_from(path.to_string())
+    }
+
+    #[test]
+    fn parses_fields() {
+        let selector = selector("$.info.balance").unwrap();
+        assert_eq!(
+            selector.steps,
+            vec![
+                Step::Field("info".to_string()),
+                Step::Field("balance".to_string())
+            ]
+        );
+
+        let value = json!({ "info": { "balance": "10" } });
+        assert_eq!(selector.select(&value), Some(&json!("10")));
+    }
+
+    #[test]
+    fn parses_indexes() {
+        let selector = selector("$.positions[1].amounts[0][2]").unwrap();
+        assert_eq!(
+            selector.steps,
+            vec![
+                Step::Field("positions".to_string()),
+                Step::Index(1),
+                Step::Field("amounts".to_string()),
+                Step::Index(0),
+                Step::Index(2),
+            ]
+        );
+
+        let value = json!({
+            "positions": [
+                {},
+                { "amounts": [["1", "2", "3"]] }
+            ]
+        });
+        assert_eq!(selector.select(&value), Some(&json!("3")));
+    }
+
+    #[test]
+    fn parses_root_index() {
+        let selector = selector("$[0].amount").unwrap();
+        assert_eq!(
+            selector.steps,
+            vec![Step::Index(0), Step::Field("amount".to_string())]
+        );
+    }
+
+    #[test]
+    fn selects_missing_value() {
+        let selector = selector("$.positions[3]").unwrap();
+        assert_eq!(selector.select(&json!({ "positions": [] })), None);
+        assert_eq!(selector.select(&json!({ "balance": "1" })), None);
+    }
+
+    #[test]
+    fn rejects_malformed_selectors() {
+        for path in [
+            "", "$", "$.", "$.a[", "$.a[1", "$.a[x]", "$.a[-1]", "$.a[1]b",
+        ] {
+            assert!(selector(path).is_err(), "{} should be rejected", path);
+        }
+    }
+}
@@ -3,10 +3,12 @@ use std::path::PathBuf;
 use config::Config;
 use serde::Deserialize;
 
+mod exporters;
 mod ranking;
 mod tokens;
 
-pub use ranking::Ranking;
+pub use exporters::{CustomExporters, SmartQueryExporter};
+pub use ranking::{Activity, Ranking};
 pub use tokens::TokenMap;
 
 pub trait ConfigLoader<'de>: std::fmt::Debug + Deserialize<'de> + Sized {
@@ -24,3 +26,5 @@ pub trait ConfigLoader<'de>: std::fmt::Debug + Deserialize<'de> + Sized {
 impl<'de> ConfigLoader<'de> for Ranking {}
 
 impl<'de> ConfigLoader<'de> for TokenMap {}
+
+impl<'de> ConfigLoader<'de> for CustomExporters {}
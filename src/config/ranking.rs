@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -6,6 +9,13 @@ pub struct ActivitiesGroup<T> {
     pub activities: T,
 }
 
+impl<T> ActivitiesGroup<T> {
+    /// Weight of one of the group activities in the total ranking.
+    pub fn activity_weight(&self, activity: &Activity) -> f32 {
+        self.weight * activity.weight
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Activity {
     pub weight: f32,
@@ -50,13 +60,110 @@ impl ActivitiesGroup<Option<Social>> {
 pub struct Archway {
     pub stake: Activity,
     pub ibc: Activity,
+    /// Number of transactions sent, only ranked when configured.
+    #[serde(default)]
+    pub transactions: Option<Activity>,
+    /// USD value of the IBC transfers received, only ranked when configured.
+    #[serde(default)]
+    pub ibc_transfers: Option<Activity>,
+    /// USD value of the contract rewards earned, only ranked when configured.
+    #[serde(default)]
+    pub rewards: Option<Activity>,
+    /// Participation rate in governance, only ranked when configured.
+    #[serde(default)]
+    pub governance: Option<GovernanceActivity>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GovernanceActivity {
+    #[serde(flatten)]
+    pub activity: Activity,
+    /// Only the proposals with a voting period started at or after this time count.
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only the latest proposals count, up to this number.
+    #[serde(default)]
+    pub last_proposals: Option<usize>,
+}
+
+impl GovernanceActivity {
+    pub fn ranking(&self, score: f64) -> f32 {
+        self.activity.ranking(score)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ArchIdActivity {
+    #[serde(flatten)]
+    pub activity: Activity,
+    /// Counts the domains whose registration expired at the export height.
+    #[serde(default)]
+    pub count_expired: bool,
+    /// Counts the subdomains besides the top-level domains.
+    #[serde(default)]
+    pub count_subdomains: bool,
+}
+
+impl ArchIdActivity {
+    pub fn ranking(&self, score: f64) -> f32 {
+        self.activity.ranking(score)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct AstrovaultActivity {
+    #[serde(flatten)]
+    pub activity: Activity,
+    /// Weights applied to the value of each pool position, keyed by pool address.
+    /// When set, only the listed pools count toward the activity score.
+    #[serde(default)]
+    pub pools: HashMap<String, f64>,
+}
+
+impl AstrovaultActivity {
+    pub fn ranking(&self, score: f64) -> f32 {
+        self.activity.ranking(score)
+    }
+
+    /// Weight of a pool position, `None` when every pool counts evenly.
+    pub fn pool_weight(&self, pool: &str) -> Option<f64> {
+        if self.pools.is_empty() {
+            return None;
+        }
+
+        Some(self.pools.get(pool).copied().unwrap_or_default())
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Ecosystem {
-    pub archid: Activity,
-    pub astrovault: Activity,
+    pub archid: ArchIdActivity,
+    pub astrovault: AstrovaultActivity,
     pub liquid_finance: Activity,
+    /// Activities of the exporters defined in the `--exporters` config file.
+    #[serde(flatten)]
+    pub others: HashMap<String, Activity>,
+}
+
+impl Ecosystem {
+    /// Activity referenced by an exporter defined in the `--exporters` config file.
+    pub fn activity(&self, key: &str) -> Option<&Activity> {
+        match key {
+            "archid" => Some(&self.archid.activity),
+            "astrovault" => Some(&self.astrovault.activity),
+            "liquid_finance" => Some(&self.liquid_finance),
+            key => self.others.get(key),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SybilPenalty {
+    /// Share of the rankings removed from a clustered address with full confidence.
+    pub penalty: f32,
+    /// Clustered addresses with a lower confidence are not penalized.
+    #[serde(default)]
+    pub min_confidence: f32,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -64,4 +171,19 @@ pub struct Ranking {
     pub social: ActivitiesGroup<Option<Social>>,
     pub archway: ActivitiesGroup<Archway>,
     pub ecosystem: ActivitiesGroup<Ecosystem>,
+    #[serde(default)]
+    pub sybil: Option<SybilPenalty>,
+}
+
+impl Ranking {
+    /// Factor applied to the rankings of an address, lowered by the sybil penalty
+    /// in proportion to the confidence of its cluster.
+    pub fn sybil_factor(&self, confidence: Option<f32>) -> f32 {
+        match (&self.sybil, confidence) {
+            (Some(sybil), Some(confidence)) if confidence >= sybil.min_confidence => {
+                (1.0 - sybil.penalty * confidence).clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        }
+    }
 }
@@ -31,6 +31,13 @@ impl TokenMap {
         self.0.get(denom)
     }
 
+    pub fn coingecko_ids(&self) -> Vec<&str> {
+        self.0
+            .values()
+            .flat_map(|info| info.coingecko_id.as_deref())
+            .collect()
+    }
+
     pub fn create_coin(
         &self,
         coin: cosmos_sdk_proto::cosmos::base::v1beta1::Coin,
@@ -1,11 +1,18 @@
-use std::{path::PathBuf, sync::Arc};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::*;
+use tokio::sync::Mutex;
 use url::Url;
 
+use crate::block::BlockId;
 use crate::clients::*;
 use crate::config::*;
 use crate::csv;
+use crate::exporters::{ErrorPolicy, ExporterKind};
+use crate::participant;
+use crate::queriers::soulbound::SocialScorePolicy;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Endpoint {
@@ -16,15 +23,38 @@ pub struct Endpoint {
 
 #[derive(Clone, Debug)]
 pub struct Context {
-    pub soulbound_address: String,
-    pub archid_address: String,
-    pub liquid_finance_address: String,
+    pub soulbound_address: Option<String>,
+    /// Addresses supplied directly instead of, or merged with, the soulbound token owners.
+    pub addresses: Option<Vec<String>>,
+    pub merge_addresses: bool,
+    /// Prefixes of other Cosmos chains to derive the participant accounts for.
+    pub derive_prefixes: Vec<String>,
+    /// Looks for sybil clusters among the participants.
+    pub sybil: bool,
+    pub archid_address: Option<String>,
+    pub liquid_finance_address: Option<String>,
+    pub liquid_finance_staking_address: Option<String>,
     pub cosmos: Arc<CosmosClient>,
-    pub astrovault: Arc<AstrovaultClient>,
-    pub coingecko: Arc<CoinGeckoClient>,
+    /// First height of the transaction history searched by the exporters.
+    pub since_height: Option<u64>,
+    /// Block requested to run the queries on, the latest one when not set.
+    pub requested_block: Option<BlockId>,
+    pub astrovault: Option<Arc<AstrovaultClient>>,
+    pub coingecko: Option<Arc<CoinGeckoClient>>,
+    pub exporters: Vec<ExporterKind>,
     pub ranking: Ranking,
     pub token_map: TokenMap,
-    output: PathBuf,
+    pub custom_exporters: CustomExporters,
+    pub social_score_policy: SocialScorePolicy,
+    pub resume: bool,
+    pub on_error: ErrorPolicy,
+    pub max_failure_rate: f64,
+    pub ranking_path: PathBuf,
+    pub token_map_path: PathBuf,
+    pub custom_exporters_path: Option<PathBuf>,
+    rpc: Endpoint,
+    output: Option<PathBuf>,
+    csv_tasks: Arc<Mutex<Vec<csv::Task>>>,
 }
 
 impl Context {
@@ -32,8 +62,31 @@ impl Context {
         ContextBuilder::default()
     }
 
+    /// Copies the context to query another block, without an output directory.
+    pub async fn at(&self, block: BlockId) -> Result<Self> {
+        let cosmos =
+            CosmosClient::new(self.rpc.url.clone(), self.rpc.req_second, Some(block)).await?;
+
+        Ok(Self {
+            cosmos: Arc::new(cosmos),
+            requested_block: Some(block),
+            output: None,
+            ..self.clone()
+        })
+    }
+
+    pub fn rpc_url(&self) -> &Url {
+        &self.rpc.url
+    }
+
+    pub fn output(&self) -> Result<&Path> {
+        self.output
+            .as_deref()
+            .ok_or(anyhow!("missing output directory"))
+    }
+
     pub fn create_output_folder(&self) -> Result<()> {
-        std::fs::create_dir_all(&self.output)?;
+        std::fs::create_dir_all(self.output()?)?;
         Ok(())
     }
 
@@ -41,8 +94,66 @@ impl Context {
     where
         T: csv::Item,
     {
-        let path = self.output.join(name).with_extension("csv");
-        csv::Writer::create(path).await
+        self.open_csv_writer(name, self.resume).await
+    }
+
+    /// Creates a writer for a file of an exporter, which writes the rows of each
+    /// address once, so an export retried or resumed after writing some of its
+    /// files doesn't duplicate the rows of the others.
+    pub async fn exporter_csv_writer<T>(&self, name: &str) -> Result<csv::Writer<T>>
+    where
+        T: csv::Item,
+    {
+        let written = match &self.output {
+            Some(output) if self.resume => {
+                csv::addresses(&output.join(name).with_extension("csv")).await?
+            }
+            _ => HashSet::new(),
+        };
+
+        Ok(self.csv_writer(name).await?.once_per_address(written))
+    }
+
+    /// Creates a writer for a report of the current run, which replaces
+    /// the one of the previous run even when resuming.
+    pub async fn report_csv_writer<T>(&self, name: &str) -> Result<csv::Writer<T>>
+    where
+        T: csv::Item,
+    {
+        self.open_csv_writer(name, false).await
+    }
+
+    async fn open_csv_writer<T>(&self, name: &str, append: bool) -> Result<csv::Writer<T>>
+    where
+        T: csv::Item,
+    {
+        // nothing is written when running without an output directory
+        let Some(output) = &self.output else {
+            return Ok(csv::Writer::sink());
+        };
+
+        let path = output.join(name).with_extension("csv");
+        let mut writer = if append {
+            csv::Writer::append(path).await?
+        } else {
+            csv::Writer::create(path).await?
+        };
+
+        if let Some(task) = writer.take_task() {
+            self.csv_tasks.lock().await.push(task);
+        }
+
+        Ok(writer)
+    }
+
+    /// Waits until the rows of every dropped CSV writer are written.
+    pub async fn flush_csv_writers(&self) -> Result<()> {
+        let tasks = std::mem::take(&mut *self.csv_tasks.lock().await);
+        for task in tasks {
+            task.await??;
+        }
+
+        Ok(())
     }
 
     pub async fn query_contract<T, R>(&self, address: String, data: &T) -> Result<R>
@@ -60,14 +171,27 @@ impl Context {
 #[derive(Default)]
 pub struct ContextBuilder {
     rpc: Option<Endpoint>,
-    height: Option<u64>,
+    block: Option<BlockId>,
+    since_height: Option<u64>,
     soulbound_address: Option<String>,
+    addresses_path: Option<PathBuf>,
+    merge_addresses: bool,
+    derive_prefixes: Vec<String>,
+    sybil: bool,
     archid_address: Option<String>,
     liquid_finance_address: Option<String>,
+    liquid_finance_staking_address: Option<String>,
     astrovault: Option<Endpoint>,
+    astrovault_batch_size: Option<usize>,
     coingecko: Option<Endpoint>,
+    exporters: Vec<ExporterKind>,
     ranking_path: Option<PathBuf>,
     token_map_path: Option<PathBuf>,
+    custom_exporters_path: Option<PathBuf>,
+    social_score_policy: SocialScorePolicy,
+    resume: bool,
+    on_error: ErrorPolicy,
+    max_failure_rate: f64,
     output: Option<PathBuf>,
 }
 
@@ -81,33 +205,62 @@ impl ContextBuilder {
         self
     }
 
-    pub fn height(mut self, height: Option<u64>) -> Self {
-        self.height = height;
+    pub fn block(mut self, block: Option<BlockId>) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn since_height(mut self, since_height: Option<u64>) -> Self {
+        self.since_height = since_height;
+        self
+    }
+
+    pub fn soulbound_address(mut self, soulbound_address: Option<String>) -> Self {
+        self.soulbound_address = soulbound_address;
         self
     }
 
-    pub fn soulbound_address(mut self, soulbound_address: String) -> Self {
-        self.soulbound_address = Some(soulbound_address);
+    pub fn addresses(mut self, addresses_path: Option<PathBuf>, merge: bool) -> Self {
+        self.addresses_path = addresses_path;
+        self.merge_addresses = merge;
         self
     }
 
-    pub fn archid_address(mut self, archid_address: String) -> Self {
-        self.archid_address = Some(archid_address);
+    pub fn derive_prefixes(mut self, prefixes: Vec<String>) -> Self {
+        self.derive_prefixes = prefixes;
         self
     }
 
-    pub fn liquid_finance_address(mut self, liquid_finance_address: String) -> Self {
-        self.liquid_finance_address = Some(liquid_finance_address);
+    pub fn sybil(mut self, sybil: bool) -> Self {
+        self.sybil = sybil;
+        self
+    }
+
+    pub fn archid_address(mut self, archid_address: Option<String>) -> Self {
+        self.archid_address = archid_address;
+        self
+    }
+
+    pub fn liquid_finance_address(mut self, liquid_finance_address: Option<String>) -> Self {
+        self.liquid_finance_address = liquid_finance_address;
+        self
+    }
+
+    pub fn liquid_finance_staking_address(
+        mut self,
+        liquid_finance_staking_address: Option<String>,
+    ) -> Self {
+        self.liquid_finance_staking_address = liquid_finance_staking_address;
         self
     }
 
     pub fn astrovault(
         mut self,
-        url: Url,
+        url: Option<Url>,
         req_second: Option<u64>,
         api_key: Option<String>,
     ) -> Self {
-        self.astrovault = Some(Endpoint {
+        self.astrovault = url.map(|url| Endpoint {
             url,
             req_second,
             api_key,
@@ -115,6 +268,11 @@ impl ContextBuilder {
         self
     }
 
+    pub fn astrovault_batch_size(mut self, batch_size: Option<usize>) -> Self {
+        self.astrovault_batch_size = batch_size;
+        self
+    }
+
     pub fn coingecko(mut self, url: Url) -> Self {
         self.coingecko = Some(Endpoint {
             url,
@@ -124,6 +282,11 @@ impl ContextBuilder {
         self
     }
 
+    pub fn exporters(mut self, exporters: Vec<ExporterKind>) -> Self {
+        self.exporters = exporters;
+        self
+    }
+
     pub fn ranking_path(mut self, ranking_path: PathBuf) -> Self {
         self.ranking_path = Some(ranking_path);
         self
@@ -134,64 +297,110 @@ impl ContextBuilder {
         self
     }
 
-    pub fn output(mut self, output: PathBuf) -> Self {
-        self.output = Some(output);
+    pub fn custom_exporters_path(mut self, custom_exporters_path: Option<PathBuf>) -> Self {
+        self.custom_exporters_path = custom_exporters_path;
+        self
+    }
+
+    pub fn social_score_policy(mut self, policy: SocialScorePolicy) -> Self {
+        self.social_score_policy = policy;
+        self
+    }
+
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    pub fn on_error(mut self, on_error: ErrorPolicy, max_failure_rate: f64) -> Self {
+        self.on_error = on_error;
+        self.max_failure_rate = max_failure_rate;
+        self
+    }
+
+    pub fn output(mut self, output: Option<PathBuf>) -> Self {
+        self.output = output;
         self
     }
 
     pub async fn build(self) -> Result<Context> {
-        let soulbound_address = self
-            .soulbound_address
-            .ok_or(anyhow!("missing soulbound address"))?;
-        let archid_address = self
-            .archid_address
-            .ok_or(anyhow!("missing archid address"))?;
-        let liquid_finance_address = self
-            .liquid_finance_address
-            .ok_or(anyhow!("missing liquid finance address"))?;
-        let output = self.output.ok_or(anyhow!("missing output directory"))?;
+        let addresses = self
+            .addresses_path
+            .as_deref()
+            .map(participant::read_addresses)
+            .transpose()?;
 
         let rpc = self.rpc.ok_or(anyhow!("missing rpc arguments"))?;
-        let cosmos = CosmosClient::new(rpc.url, rpc.req_second, self.height).await?;
-
-        let av_endpoint = self
-            .astrovault
-            .clone()
-            .ok_or(anyhow!("missing astrovault arguments"))?;
-        let astrovault = AstrovaultClient::builder(av_endpoint.url.clone())
-            .req_second(av_endpoint.req_second)
-            .api_key(av_endpoint.api_key.clone())
-            .build()
-            .await?;
-
-        let coingecko_endpoint = self
-            .coingecko
-            .clone()
-            .ok_or(anyhow!("missing coingecko arguments"))?;
-        let coingecko = CoinGeckoClient::builder(coingecko_endpoint.url.clone())
-            .build()
-            .await?;
+        let cosmos = CosmosClient::new(rpc.url.clone(), rpc.req_second, self.block).await?;
 
-        let ranking_path = self
-            .ranking_path
-            .ok_or(anyhow!("missing ranking config file path"))?;
-        let ranking = Ranking::load(ranking_path)?;
+        let astrovault = match self.astrovault {
+            Some(endpoint) => {
+                let client = AstrovaultClient::builder(endpoint.url)
+                    .req_second(endpoint.req_second)
+                    .api_key(endpoint.api_key)
+                    .batch_size(self.astrovault_batch_size)
+                    .build()
+                    .await?;
+                Some(Arc::new(client))
+            }
+            None => None,
+        };
 
         let token_map_path = self
             .token_map_path
             .ok_or(anyhow!("missing token map config file path"))?;
-        let token_map = TokenMap::load(token_map_path)?;
+        let token_map = TokenMap::load(token_map_path.clone())?;
+
+        // prices are only fetched when an exporter needs them
+        let coingecko = match self.coingecko {
+            Some(endpoint) if self.exporters.iter().any(ExporterKind::needs_prices) => {
+                let client = CoinGeckoClient::builder(endpoint.url)
+                    .prefetch(token_map.coingecko_ids())
+                    .build()
+                    .await?;
+                Some(Arc::new(client))
+            }
+            _ => None,
+        };
+
+        let ranking_path = self
+            .ranking_path
+            .ok_or(anyhow!("missing ranking config file path"))?;
+        let ranking = Ranking::load(ranking_path.clone())?;
+
+        let custom_exporters = match &self.custom_exporters_path {
+            Some(path) => CustomExporters::load(path.clone())?,
+            None => CustomExporters::default(),
+        };
 
         let ctx = Context {
-            soulbound_address,
-            archid_address,
-            liquid_finance_address,
+            soulbound_address: self.soulbound_address,
+            addresses,
+            merge_addresses: self.merge_addresses,
+            derive_prefixes: self.derive_prefixes,
+            sybil: self.sybil,
+            archid_address: self.archid_address,
+            liquid_finance_address: self.liquid_finance_address,
+            liquid_finance_staking_address: self.liquid_finance_staking_address,
             cosmos: Arc::new(cosmos),
-            astrovault: Arc::new(astrovault),
-            coingecko: Arc::new(coingecko),
+            since_height: self.since_height,
+            requested_block: self.block,
+            astrovault,
+            coingecko,
+            exporters: self.exporters,
             ranking,
             token_map,
-            output,
+            custom_exporters,
+            social_score_policy: self.social_score_policy,
+            resume: self.resume,
+            on_error: self.on_error,
+            max_failure_rate: self.max_failure_rate,
+            ranking_path,
+            token_map_path,
+            custom_exporters_path: self.custom_exporters_path,
+            rpc,
+            output: self.output,
+            csv_tasks: Arc::new(Mutex::new(Vec::new())),
         };
 
         Ok(ctx)
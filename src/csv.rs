@@ -1,11 +1,14 @@
+use std::collections::HashSet;
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
-use tokio::fs::File;
+use tokio::fs::{File, OpenOptions};
 use tokio::io::{self, AsyncWriteExt, BufWriter};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 
 const DEFAULT_BUFFER_SIZE: usize = 1024;
 const SEPARATOR: &str = ";";
@@ -19,15 +22,30 @@ pub trait Item {
     fn rows(self) -> Vec<Row>;
 }
 
+pub type Task = JoinHandle<io::Result<()>>;
+
+/// Rows sent to the task writing the file, acknowledged once flushed.
+struct Batch {
+    rows: Vec<Row>,
+    written: oneshot::Sender<()>,
+}
+
 pub struct Writer<T: Item> {
-    tx: mpsc::Sender<Row>,
+    tx: Option<mpsc::Sender<Batch>>,
+    task: Option<Task>,
+    /// Addresses already written, when each one is only written once.
+    written: Option<Mutex<HashSet<String>>>,
     _phantom: PhantomData<T>,
 }
 
 impl<T: Item> Writer<T> {
     pub async fn create(path: PathBuf) -> Result<Self> {
+        let file = File::create(path).await?;
+        let (tx, task) = writer_channel(file);
         let writer = Self {
-            tx: writer_channel(path).await?,
+            tx: Some(tx),
+            task: Some(task),
+            written: None,
             _phantom: PhantomData,
         };
         writer.write_header().await?;
@@ -35,32 +53,149 @@ impl<T: Item> Writer<T> {
         Ok(writer)
     }
 
+    /// Creates a writer that discards all the rows.
+    pub fn sink() -> Self {
+        Self {
+            tx: None,
+            task: None,
+            written: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Appends rows to an existing file, only writing
+    /// the header when the file is new or empty.
+    pub async fn append(path: PathBuf) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let is_empty = file.metadata().await?.len() == 0;
+
+        let (tx, task) = writer_channel(file);
+        let writer = Self {
+            tx: Some(tx),
+            task: Some(task),
+            written: None,
+            _phantom: PhantomData,
+        };
+        if is_empty {
+            writer.write_header().await?;
+        }
+
+        Ok(writer)
+    }
+
     async fn write_header(&self) -> Result<()> {
         let header = T::header().iter().map(|s| s.to_string()).collect();
-        self.tx.send(header).await?;
-        Ok(())
+        self.send(vec![header]).await
     }
 
+    /// Only writes the rows of the addresses not written yet, read from the
+    /// first column, starting with the addresses already in the file.
+    pub fn once_per_address(mut self, written: HashSet<String>) -> Self {
+        self.written = Some(Mutex::new(written));
+        self
+    }
+
+    /// Writes the rows of the item, returning once they are flushed to the file.
     pub async fn write(&self, item: T) -> Result<()> {
-        for row in item.rows() {
-            self.tx.send(row).await?;
+        let rows = item.rows();
+        let address = rows.first().and_then(|row| row.first()).cloned();
+
+        if let (Some(written), Some(address)) = (&self.written, &address) {
+            if written.lock().unwrap().contains(address) {
+                tracing::debug!(address, "rows already written, skipping");
+                return Ok(());
+            }
         }
+
+        self.send(rows).await?;
+
+        // only recorded once flushed, so a failed write is written again on retry
+        if let (Some(written), Some(address)) = (&self.written, address) {
+            written.lock().unwrap().insert(address);
+        }
+
         Ok(())
     }
+
+    /// Waits until all the rows are written to the file.
+    pub async fn close(mut self) -> Result<()> {
+        self.tx = None;
+        if let Some(task) = self.task.take() {
+            task.await??;
+        }
+        Ok(())
+    }
+
+    /// Takes the task writing the rows, which finishes once the writer is dropped.
+    pub fn take_task(&mut self) -> Option<Task> {
+        self.task.take()
+    }
+
+    async fn send(&self, rows: Vec<Row>) -> Result<()> {
+        let Some(tx) = &self.tx else {
+            return Ok(());
+        };
+
+        let (written, ack) = oneshot::channel();
+        tx.send(Batch { rows, written })
+            .await
+            .map_err(|_| anyhow!("CSV writer closed"))?;
+        // the task drops the sender without acknowledging when writing fails
+        ack.await
+            .map_err(|_| anyhow!("failed to write the CSV rows"))?;
+
+        Ok(())
+    }
+}
+
+/// Reads all the rows of a file, including its header.
+pub async fn read(path: &Path) -> Result<Vec<Row>> {
+    let content = tokio::fs::read_to_string(path).await?;
+    let rows = content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split(SEPARATOR).map(str::to_string).collect())
+        .collect();
+
+    Ok(rows)
+}
+
+/// Addresses in the first column of the rows of a file, empty when it doesn't exist.
+pub async fn addresses(path: &Path) -> Result<HashSet<String>> {
+    if !tokio::fs::try_exists(path).await? {
+        return Ok(HashSet::new());
+    }
+
+    let addresses = read(path)
+        .await?
+        .into_iter()
+        .skip(1)
+        .filter_map(|row| row.into_iter().next())
+        .collect();
+
+    Ok(addresses)
 }
 
-async fn writer_channel(path: PathBuf) -> Result<mpsc::Sender<Row>> {
-    let (tx, mut rx) = mpsc::channel::<Vec<String>>(DEFAULT_BUFFER_SIZE);
-    let file = File::create(path).await?;
+fn writer_channel(file: File) -> (mpsc::Sender<Batch>, Task) {
+    let (tx, mut rx) = mpsc::channel::<Batch>(DEFAULT_BUFFER_SIZE);
 
-    tokio::spawn(async move {
+    let task = tokio::spawn(async move {
         let mut writer = BufWriter::new(file);
-        while let Some(row) = rx.recv().await {
-            let buffer = row.join(SEPARATOR);
-            tracing::trace!(?row, "writing row to file");
-            writer.write_all(buffer.as_bytes()).await?;
-            writer.write_all(b"\n").await?;
+        while let Some(batch) = rx.recv().await {
+            for row in batch.rows {
+                let buffer = row.join(SEPARATOR);
+                tracing::trace!(?row, "writing row to file");
+                writer.write_all(buffer.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+            }
             writer.flush().await?;
+
+            // the writer may not wait for the acknowledgement
+            let _ = batch.written.send(());
         }
 
         writer.shutdown().await?;
@@ -68,5 +203,5 @@ async fn writer_channel(path: PathBuf) -> Result<mpsc::Sender<Row>> {
         Ok::<_, io::Error>(())
     });
 
-    Ok(tx)
+    (tx, task)
 }
@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::csv;
+use crate::exporters::ExporterKind;
+use crate::prelude::*;
+
+const DIFF_FILE: &str = "diff.csv";
+
+/// Kind of change of an address between two exports.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    Added,
+    Removed,
+    Ranking,
+    Value,
+}
+
+impl Change {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Ranking => "ranking",
+            Self::Value => "value",
+        }
+    }
+}
+
+/// A change of a single column for an address, or for one of its entries.
+#[derive(Clone, Debug)]
+pub struct AddressChange {
+    /// Name of the file the change comes from, without its extension.
+    pub file: &'static str,
+    pub address: String,
+    /// Values of the columns identifying the entry, empty in the files
+    /// with one row per address.
+    pub entry: String,
+    pub change: Change,
+    pub column: String,
+    pub old: String,
+    pub new: String,
+    /// Difference between the values, when both are numbers.
+    pub delta: Option<f64>,
+}
+
+impl csv::Item for AddressChange {
+    fn header() -> csv::Header {
+        vec![
+            "file", "address", "entry", "change", "column", "old", "new", "delta",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.file.to_string(),
+            self.address,
+            self.entry,
+            self.change.name().to_string(),
+            self.column,
+            self.old,
+            self.new,
+            self.delta.map(|d| format!("{:.2}", d)).unwrap_or_default(),
+        ]]
+    }
+}
+
+/// Rows of an exporter file, indexed by address and entry.
+type Snapshot = HashMap<(String, String), Vec<String>>;
+
+/// Compares the files of two exports, writing the changes to `diff.csv` in
+/// the output directory and printing a summary.
+///
+/// Ranking changes are only reported when they are greater than the threshold.
+pub async fn run(
+    old: &Path,
+    new: &Path,
+    output: &Path,
+    exporters: &[ExporterKind],
+    threshold: f64,
+) -> Result<()> {
+    let csv: csv::Writer<AddressChange> = csv::Writer::create(output.join(DIFF_FILE)).await?;
+
+    for kind in exporters {
+        let files = std::iter::once((kind.name(), kind.header(), vec![])).chain(
+            kind.entry_files()
+                .into_iter()
+                .map(|file| (file.name, file.header, file.entry)),
+        );
+
+        for (name, header, entry) in files {
+            let Some(changes) = diff_file(old, new, name, &header, &entry, threshold).await? else {
+                continue;
+            };
+            print_summary(name, &changes);
+
+            for change in changes {
+                csv.write(change).await?;
+            }
+        }
+    }
+
+    csv.close().await
+}
+
+/// Compares a file of both exports on their shared columns,
+/// `None` when it can't be compared.
+async fn diff_file(
+    old: &Path,
+    new: &Path,
+    name: &'static str,
+    header: &csv::Header,
+    entry: &[&str],
+    threshold: f64,
+) -> Result<Option<Vec<AddressChange>>> {
+    let file = Path::new(name).with_extension("csv");
+    let (old_path, new_path) = (old.join(&file), new.join(&file));
+
+    if !old_path.exists() || !new_path.exists() {
+        tracing::warn!(file = name, "file missing in one of the exports, skipping");
+        return Ok(None);
+    }
+
+    let (old_columns, old_rows) = load(&old_path, entry).await?;
+    let (new_columns, new_rows) = load(&new_path, entry).await?;
+
+    // exports from other versions may have different columns,
+    // so only the ones in both files and the current schema are compared
+    let shared: csv::Header = header
+        .iter()
+        .filter(|column| has_column(&old_columns, column) && has_column(&new_columns, column))
+        .copied()
+        .collect();
+    if shared.first() != Some(&"address") || !entry.iter().all(|c| shared.contains(c)) {
+        tracing::warn!(
+            file = name,
+            "no address or entry columns in one of the exports, skipping"
+        );
+        return Ok(None);
+    }
+    if old_columns != *header || new_columns != *header {
+        tracing::warn!(
+            file = name,
+            columns = shared.join(","),
+            "columns differ between the exports, comparing the shared ones"
+        );
+    }
+
+    let old_rows = project(old_rows, &old_columns, &shared);
+    let new_rows = project(new_rows, &new_columns, &shared);
+
+    Ok(Some(compare(
+        name, &shared, entry, &old_rows, &new_rows, threshold,
+    )))
+}
+
+/// Loads an exporter file with its columns, indexing the rows by the address
+/// in the first column and the values of the entry columns.
+async fn load(path: &Path, entry: &[&str]) -> Result<(Vec<String>, Snapshot)> {
+    let mut rows = csv::read(path).await?.into_iter();
+
+    let columns = rows.next().unwrap_or_default();
+    let entry: Vec<usize> = entry
+        .iter()
+        .filter_map(|column| columns.iter().position(|c| c == column))
+        .collect();
+
+    let (mut dropped, mut duplicated) = (0, 0);
+    let mut snapshot = Snapshot::new();
+    for row in rows {
+        if row.len() != columns.len() {
+            dropped += 1;
+            continue;
+        }
+
+        let key = entry_key(&row, &entry);
+        if snapshot.insert((row[0].clone(), key), row).is_some() {
+            duplicated += 1;
+        }
+    }
+
+    if dropped > 0 {
+        tracing::warn!(
+            path = %path.display(),
+            count = dropped,
+            "rows not matching the columns of the header, skipping"
+        );
+    }
+    if duplicated > 0 {
+        tracing::warn!(
+            path = %path.display(),
+            count = duplicated,
+            "rows repeating an address or entry, keeping the last ones"
+        );
+    }
+
+    Ok((columns, snapshot))
+}
+
+fn entry_key(row: &[String], entry: &[usize]) -> String {
+    let values: Vec<&str> = entry.iter().map(|&i| row[i].as_str()).collect();
+    values.join(",")
+}
+
+fn has_column(columns: &[String], column: &str) -> bool {
+    columns.iter().any(|c| c == column)
+}
+
+/// Keeps the given columns of the rows, in their order.
+fn project(snapshot: Snapshot, columns: &[String], shared: &csv::Header) -> Snapshot {
+    let indexes: Vec<usize> = shared
+        .iter()
+        .filter_map(|column| columns.iter().position(|c| c == column))
+        .collect();
+
+    snapshot
+        .into_iter()
+        .map(|(key, row)| {
+            let row = indexes.iter().map(|&i| row[i].clone()).collect();
+            (key, row)
+        })
+        .collect()
+}
+
+fn compare(
+    file: &'static str,
+    header: &csv::Header,
+    entry: &[&str],
+    old: &Snapshot,
+    new: &Snapshot,
+    threshold: f64,
+) -> Vec<AddressChange> {
+    let ranking = header.iter().position(|column| *column == "ranking");
+    // added and removed rows are reported with their ranking, when the file has one
+    let ranking_column = match ranking {
+        Some(_) => "ranking".to_string(),
+        None => String::new(),
+    };
+    let change = |(address, entry): &(String, String), change, column: String| AddressChange {
+        file,
+        address: address.clone(),
+        entry: entry.clone(),
+        change,
+        column,
+        old: String::new(),
+        new: String::new(),
+        delta: None,
+    };
+    let mut changes = Vec::new();
+
+    for (key, row) in new.iter() {
+        let Some(old_row) = old.get(key) else {
+            changes.push(AddressChange {
+                new: ranking.map(|i| row[i].clone()).unwrap_or_default(),
+                ..change(key, Change::Added, ranking_column.clone())
+            });
+            continue;
+        };
+
+        // the address and the entry identify the row, so they never change
+        for (i, column) in header.iter().enumerate().skip(1) {
+            if entry.contains(column) {
+                continue;
+            }
+
+            let (old_value, new_value) = (&old_row[i], &row[i]);
+            if old_value == new_value {
+                continue;
+            }
+
+            let delta = match (old_value.parse::<f64>(), new_value.parse::<f64>()) {
+                (std::result::Result::Ok(old), std::result::Result::Ok(new)) => Some(new - old),
+                _ => None,
+            };
+
+            let kind = if Some(i) == ranking {
+                if delta.is_some_and(|delta| delta.abs() <= threshold) {
+                    continue;
+                }
+                Change::Ranking
+            } else {
+                Change::Value
+            };
+
+            changes.push(AddressChange {
+                old: old_value.clone(),
+                new: new_value.clone(),
+                delta,
+                ..change(key, kind, column.to_string())
+            });
+        }
+    }
+
+    for (key, row) in old.iter().filter(|(key, _)| !new.contains_key(*key)) {
+        changes.push(AddressChange {
+            old: ranking.map(|i| row[i].clone()).unwrap_or_default(),
+            ..change(key, Change::Removed, ranking_column.clone())
+        });
+    }
+
+    changes.sort_by(|a, b| (&a.address, &a.entry, a.change).cmp(&(&b.address, &b.entry, b.change)));
+    changes
+}
+
+fn print_summary(file: &str, changes: &[AddressChange]) {
+    let mut counts: BTreeMap<Change, usize> = BTreeMap::new();
+    for change in changes {
+        *counts.entry(change.change).or_default() += 1;
+    }
+
+    let count = |change: Change| counts.get(&change).copied().unwrap_or_default();
+    println!(
+        "{}: {} added, {} removed, {} ranking changes, {} value changes",
+        file,
+        count(Change::Added),
+        count(Change::Removed),
+        count(Change::Ranking),
+        count(Change::Value)
+    );
+
+    let mut movers: Vec<&AddressChange> = changes
+        .iter()
+        .filter(|change| change.change == Change::Ranking)
+        .collect();
+    movers.sort_by(|a, b| {
+        let (a, b) = (a.delta.unwrap_or_default(), b.delta.unwrap_or_default());
+        b.abs().total_cmp(&a.abs())
+    });
+
+    for change in movers.iter().take(5) {
+        let label = match change.entry.as_str() {
+            "" => change.address.clone(),
+            entry => format!("{} ({})", change.address, entry),
+        };
+        println!(
+            "  {} {} -> {} ({:+.2})",
+            label,
+            change.old,
+            change.new,
+            change.delta.unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(rows: &[&[&str]], entry: &[usize]) -> Snapshot {
+        rows.iter()
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(|s| s.to_string()).collect();
+                ((row[0].clone(), entry_key(&row, entry)), row)
+            })
+            .collect()
+    }
+
+    fn summary(changes: &[AddressChange]) -> Vec<(&str, &str, Change, &str)> {
+        changes
+            .iter()
+            .map(|c| {
+                (
+                    c.address.as_str(),
+                    c.entry.as_str(),
+                    c.change,
+                    c.column.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_added_and_removed_addresses() {
+        let header = vec!["address", "ranking", "score"];
+        let old = snapshot(
+            &[&["archway1a", "1.00", "5"], &["archway1b", "2.00", "7"]],
+            &[],
+        );
+        let new = snapshot(
+            &[&["archway1a", "1.00", "5"], &["archway1c", "3.00", "9"]],
+            &[],
+        );
+
+        let changes = compare("staking", &header, &[], &old, &new, 0.0);
+
+        assert_eq!(
+            summary(&changes),
+            [
+                ("archway1b", "", Change::Removed, "ranking"),
+                ("archway1c", "", Change::Added, "ranking"),
+            ]
+        );
+        assert_eq!(changes[0].old, "2.00");
+        assert_eq!(changes[1].new, "3.00");
+    }
+
+    #[test]
+    fn ignores_ranking_changes_under_threshold() {
+        let header = vec!["address", "ranking", "score"];
+        let old = snapshot(
+            &[&["archway1a", "1.00", "5"], &["archway1b", "2.00", "7"]],
+            &[],
+        );
+        let new = snapshot(
+            &[&["archway1a", "1.05", "5"], &["archway1b", "2.50", "7"]],
+            &[],
+        );
+
+        let changes = compare("staking", &header, &[], &old, &new, 0.1);
+
+        assert_eq!(
+            summary(&changes),
+            [("archway1b", "", Change::Ranking, "ranking")]
+        );
+        assert_eq!(changes[0].delta, Some(0.5));
+    }
+
+    #[test]
+    fn reports_value_changes() {
+        let header = vec!["address", "ranking", "score"];
+        let old = snapshot(&[&["archway1a", "1.00", "5"]], &[]);
+        let new = snapshot(&[&["archway1a", "1.00", "8"]], &[]);
+
+        let changes = compare("staking", &header, &[], &old, &new, 0.0);
+
+        assert_eq!(
+            summary(&changes),
+            [("archway1a", "", Change::Value, "score")]
+        );
+        assert_eq!(changes[0].delta, Some(3.0));
+    }
+
+    #[test]
+    fn keys_entry_files_by_address_and_entry() {
+        let header = vec!["address", "contract", "balance"];
+        let entry = [1];
+        let old = snapshot(
+            &[
+                &["archway1a", "archway1token", "10"],
+                &["archway1a", "archway1old", "1"],
+            ],
+            &entry,
+        );
+        let new = snapshot(
+            &[
+                &["archway1a", "archway1token", "12"],
+                &["archway1a", "archway1new", "4"],
+            ],
+            &entry,
+        );
+
+        let changes = compare("cw20-balances", &header, &["contract"], &old, &new, 0.0);
+
+        // without a ranking column, added and removed rows have no column
+        assert_eq!(
+            summary(&changes),
+            [
+                ("archway1a", "archway1new", Change::Added, ""),
+                ("archway1a", "archway1old", Change::Removed, ""),
+                ("archway1a", "archway1token", Change::Value, "balance"),
+            ]
+        );
+    }
+}
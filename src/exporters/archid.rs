@@ -1,64 +1,136 @@
 use async_trait::async_trait;
+use futures::prelude::*;
+use serde::Serialize;
 
+use crate::participant::Participant;
 use crate::prelude::*;
-use crate::queriers::soulbound::TokenInfo;
-use crate::{csv, queriers::archid::ArchIdRegistry, Context};
+use crate::queriers::archid::{ArchIdRegistry, DomainRecord};
+use crate::{csv, Context};
 
-use super::Exporter;
+use super::{Exporter, Inspection};
 
 pub struct ArchId {
     ctx: Arc<Context>,
     csv: csv::Writer<AddressNames>,
     archid: ArchIdRegistry,
+    /// Address of the CW721 contract of the ArchID names.
+    cw721: String,
 }
 
 impl ArchId {
     pub async fn create(ctx: Arc<Context>) -> Result<Self> {
-        let csv = ctx.csv_writer("archid").await?;
-        let archid = ArchIdRegistry::new(ctx.clone());
+        let csv = ctx.exporter_csv_writer("archid").await?;
+        let archid = ArchIdRegistry::new(ctx.clone())?;
+        let cw721 = archid.cw721_address().await?;
 
-        Ok(Self { ctx, csv, archid })
+        Ok(Self {
+            ctx,
+            csv,
+            archid,
+            cw721,
+        })
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<AddressNames> {
+        let names = self.archid.resolve_domains(owner.address.clone()).await?;
+        let records: Vec<DomainRecord> = stream::iter(names)
+            .map(|name| self.archid.record(&self.cw721, name))
+            .buffered(10)
+            .try_collect()
+            .await?;
+
+        // expiration is checked at the time of the exported block
+        let time = self.ctx.cosmos.block.time;
+        let activity = &self.ctx.ranking.ecosystem.activities.archid;
+
+        let mut names = AddressNames {
+            address: owner.address.clone(),
+            ..Default::default()
+        };
+        for record in records.iter() {
+            let expired = record.is_expired(time);
+            match (record.subdomain, expired) {
+                (true, _) => names.subdomains += 1,
+                (false, true) => names.expired_domains += 1,
+                (false, false) => names.active_domains += 1,
+            }
+
+            if (!expired || activity.count_expired)
+                && (!record.subdomain || activity.count_subdomains)
+            {
+                names.score += 1;
+            }
+        }
+        names.names = records.into_iter().map(|record| record.name).collect();
+        names.ranking =
+            self.rank(names.score as f64) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        Ok(names)
     }
 }
 
 #[async_trait]
 impl Exporter for ArchId {
-    #[tracing::instrument(name = "archid::export", skip_all, fields(address = token.owner))]
-    async fn export(&self, token: &TokenInfo) -> Result<()> {
-        tracing::info!("exporting ArchID domains");
+    fn name(&self) -> &'static str {
+        "archid"
+    }
 
-        let names = self.archid.resolve_domains(token.owner.clone()).await?;
-        let ranking = self
-            .ctx
-            .ranking
-            .ecosystem
-            .activities
-            .archid
-            .ranking(names.len() as f64);
-
-        let assets = AddressNames {
-            address: token.owner.clone(),
-            names,
-            ranking,
-        };
+    #[tracing::instrument(name = "archid::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting ArchID domains");
 
+        let assets = self.collect(owner).await?;
         self.csv.write(assets).await?;
 
         tracing::info!("ArchID domains export finished");
 
         Ok(())
     }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let assets = self.collect(owner).await?;
+        let ecosystem = &self.ctx.ranking.ecosystem;
+        let weight = ecosystem.activity_weight(&ecosystem.activities.archid.activity);
+
+        Inspection::new(
+            self.name(),
+            assets.score as f64,
+            assets.ranking,
+            weight,
+            &assets,
+        )
+    }
+
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx.ranking.ecosystem.activities.archid.ranking(score)
+    }
 }
 
+#[derive(Default, Serialize)]
 pub struct AddressNames {
     address: String,
     names: Vec<String>,
+    /// Top-level domains not expired at the export height.
+    active_domains: usize,
+    /// Top-level domains expired at the export height.
+    expired_domains: usize,
+    subdomains: usize,
+    /// Names counted toward the ranking.
+    score: usize,
     ranking: f32,
 }
 
 impl csv::Item for AddressNames {
     fn header() -> csv::Header {
-        vec!["address", "ranking", "domains", "names"]
+        vec![
+            "address",
+            "ranking",
+            "domains",
+            "active_domains",
+            "expired_domains",
+            "subdomains",
+            "names",
+        ]
     }
 
     fn rows(self) -> Vec<csv::Row> {
@@ -66,6 +138,9 @@ impl csv::Item for AddressNames {
             self.address.clone(),
             format!("{:.2}", self.ranking),
             self.names.len().to_string(),
+            self.active_domains.to_string(),
+            self.expired_domains.to_string(),
+            self.subdomains.to_string(),
             self.names.join(", "),
         ]]
     }
@@ -1,65 +1,269 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
 use async_trait::async_trait;
+use serde::Serialize;
 
-use crate::{csv, prelude::*, queriers::soulbound::TokenInfo};
+use crate::clients::{AstrovaultClient, AstrovaultError, PoolPosition, WalletSummary};
+use crate::{csv, participant::Participant, prelude::*};
 
-use super::Exporter;
+use super::{Exporter, Inspection};
 
 pub struct Astrovault {
     ctx: Arc<Context>,
+    client: Arc<AstrovaultClient>,
     csv: csv::Writer<AstrovaultPosition>,
+    positions_csv: csv::Writer<AddressPoolPositions>,
+    wallets: OnceLock<HashMap<String, BatchedWallet>>,
+}
+
+/// Wallet and positions of an address prefetched with the batched endpoints.
+struct BatchedWallet {
+    summary: WalletSummary,
+    positions: Vec<PoolPosition>,
 }
 
 impl Astrovault {
     pub async fn create(ctx: Arc<Context>) -> Result<Self> {
-        let csv = ctx.csv_writer("astrovault").await?;
-        Ok(Self { ctx, csv })
+        let client = ctx
+            .astrovault
+            .clone()
+            .ok_or(anyhow!("missing astrovault client"))?;
+        let csv = ctx.exporter_csv_writer("astrovault").await?;
+        let positions_csv = ctx.exporter_csv_writer("astrovault-positions").await?;
+        Ok(Self {
+            ctx,
+            client,
+            csv,
+            positions_csv,
+            wallets: OnceLock::new(),
+        })
     }
-}
 
-#[async_trait]
-impl Exporter for Astrovault {
-    #[tracing::instrument(name = "astrovault::export", skip_all, fields(address = token.owner))]
-    async fn export(&self, token: &TokenInfo) -> Result<()> {
-        tracing::info!("exporting Astrovault stats and tvl");
+    /// Collects the wallet position, and its pool positions unless
+    /// the wallet is unknown to Astrovault.
+    async fn collect(
+        &self,
+        owner: &Participant,
+    ) -> Result<(AstrovaultPosition, Option<AddressPoolPositions>)> {
+        match self.position(owner.address.as_str()).await {
+            Err(err) if is_not_found(&err) => {
+                tracing::warn!(%err, "wallet not found in Astrovault");
+                let position = AstrovaultPosition::failed(owner.address.clone(), &err);
+                Ok((position, None))
+            }
+            position => {
+                let (mut position, pool_positions) = position?;
+                position.ranking *= self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+                Ok((position, Some(pool_positions)))
+            }
+        }
+    }
 
-        let stats = self.ctx.astrovault.stats(token.owner.as_str()).await?;
-        let tvl = self.ctx.astrovault.tvl(token.owner.as_str()).await?;
+    /// Pool positions of a single address, empty when Astrovault has none for it.
+    async fn positions(&self, address: &str) -> Result<Vec<PoolPosition>> {
+        match self.client.positions(address).await {
+            Err(err) if is_not_found(&err) => Ok(vec![]),
+            positions => positions,
+        }
+    }
 
-        let ranking = self
-            .ctx
-            .ranking
-            .ecosystem
-            .activities
-            .astrovault
-            .ranking(tvl.tvl);
+    async fn position(&self, address: &str) -> Result<(AstrovaultPosition, AddressPoolPositions)> {
+        let batched = self.wallets.get().and_then(|wallets| wallets.get(address));
+
+        let (has_lpd, has_traded, tvl, positions) = match batched {
+            Some(BatchedWallet { summary, positions }) => (
+                summary.has_lpd,
+                summary.has_traded,
+                summary.tvl,
+                positions.clone(),
+            ),
+            None => {
+                let (stats, tvl, positions) = futures::try_join!(
+                    self.client.stats(address),
+                    self.client.tvl(address),
+                    self.positions(address)
+                )?;
+                (stats.has_lpd, stats.has_traded, tvl.tvl, positions)
+            }
+        };
+
+        let activity = &self.ctx.ranking.ecosystem.activities.astrovault;
+        let positions: Vec<WeightedPosition> = positions
+            .into_iter()
+            .map(|position| WeightedPosition {
+                weight: activity.pool_weight(&position.pool),
+                position,
+            })
+            .collect();
+
+        let score = if activity.pools.is_empty() {
+            tvl
+        } else {
+            positions
+                .iter()
+                .map(|position| position.position.usd * position.weight.unwrap_or_default())
+                .sum()
+        };
+        tracing::debug!(tvl, score, pools = positions.len(), "Astrovault positions");
+
+        let ranking = self.rank(score);
 
         let position = AstrovaultPosition {
-            address: token.owner.clone(),
-            has_lpd: stats.has_lpd,
-            has_traded: stats.has_traded,
-            tvl: tvl.tvl,
+            address: address.to_string(),
+            has_lpd,
+            has_traded,
+            tvl,
+            score,
             ranking,
+            error: None,
+        };
+        let pool_positions = AddressPoolPositions {
+            address: address.to_string(),
+            positions,
+        };
+
+        Ok((position, pool_positions))
+    }
+}
+
+#[async_trait]
+impl Exporter for Astrovault {
+    fn name(&self) -> &'static str {
+        "astrovault"
+    }
+
+    #[tracing::instrument(name = "astrovault::prepare", skip_all)]
+    async fn prepare(&self, owners: &[Participant]) -> Result<()> {
+        let Some(batch_size) = self.client.batch_size() else {
+            return Ok(());
         };
 
+        tracing::info!(batch_size, "fetching Astrovault wallets in batches");
+
+        let addresses: Vec<String> = owners.iter().map(|owner| owner.address.clone()).collect();
+        let mut wallets = HashMap::new();
+        for chunk in addresses.chunks(batch_size) {
+            let batch = futures::try_join!(
+                self.client.wallets(chunk),
+                self.client.wallets_positions(chunk)
+            );
+            // the addresses left out are fetched one by one during the export
+            let (summaries, positions) = match batch {
+                std::result::Result::Ok(batch) => batch,
+                Err(err) => {
+                    tracing::warn!(
+                        count = chunk.len(),
+                        "failed to fetch a batch of Astrovault wallets: {:#}",
+                        err
+                    );
+                    continue;
+                }
+            };
+            let mut positions: HashMap<String, Vec<PoolPosition>> = positions
+                .into_iter()
+                .map(|wallet| (wallet.address, wallet.positions))
+                .collect();
+
+            wallets.extend(summaries.into_iter().map(|summary| {
+                // wallets without liquidity may be left out of the positions response
+                let positions = positions.remove(&summary.address).unwrap_or_default();
+                (
+                    summary.address.clone(),
+                    BatchedWallet { summary, positions },
+                )
+            }));
+        }
+        tracing::info!(count = wallets.len(), "Astrovault wallets fetched");
+
+        self.wallets
+            .set(wallets)
+            .map_err(|_| anyhow!("Astrovault wallets already fetched"))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "astrovault::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting Astrovault stats and tvl");
+
+        let (position, pool_positions) = self.collect(owner).await?;
         self.csv.write(position).await?;
+        if let Some(pool_positions) = pool_positions {
+            self.positions_csv.write(pool_positions).await?;
+        }
 
         tracing::info!("Astrovault stats and tvl export finished");
 
         Ok(())
     }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let (position, pool_positions) = self.collect(owner).await?;
+        let ecosystem = &self.ctx.ranking.ecosystem;
+        let weight = ecosystem.activity_weight(&ecosystem.activities.astrovault.activity);
+        let data = serde_json::json!({
+            "position": position,
+            "pools": pool_positions,
+        });
+
+        Inspection::new(self.name(), position.score, position.ranking, weight, &data)
+    }
+
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx
+            .ranking
+            .ecosystem
+            .activities
+            .astrovault
+            .ranking(score)
+    }
+}
+
+fn is_not_found(err: &Error) -> bool {
+    matches!(
+        err.downcast_ref::<AstrovaultError>(),
+        Some(AstrovaultError::NotFound)
+    )
 }
 
+#[derive(Serialize)]
 pub struct AstrovaultPosition {
     address: String,
     has_lpd: bool,
     has_traded: bool,
     tvl: f64,
+    /// Value of the pool positions, weighted by pool when configured.
+    score: f64,
     ranking: f32,
+    error: Option<String>,
+}
+
+impl AstrovaultPosition {
+    fn failed(address: String, err: &Error) -> Self {
+        Self {
+            address,
+            has_lpd: false,
+            has_traded: false,
+            tvl: 0.0,
+            score: 0.0,
+            ranking: 0.0,
+            error: Some(err.to_string()),
+        }
+    }
 }
 
 impl csv::Item for AstrovaultPosition {
     fn header() -> csv::Header {
-        vec!["address", "ranking", "has_lpd", "has_traded", "tvl"]
+        vec![
+            "address",
+            "ranking",
+            "has_lpd",
+            "has_traded",
+            "tvl",
+            "score",
+            "error",
+        ]
     }
 
     fn rows(self) -> Vec<csv::Row> {
@@ -69,6 +273,48 @@ impl csv::Item for AstrovaultPosition {
             self.has_lpd.to_string(),
             self.has_traded.to_string(),
             self.tvl.to_string(),
+            self.score.to_string(),
+            self.error.unwrap_or_default(),
         ]]
     }
 }
+
+#[derive(Serialize)]
+struct WeightedPosition {
+    position: PoolPosition,
+    weight: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct AddressPoolPositions {
+    address: String,
+    positions: Vec<WeightedPosition>,
+}
+
+impl csv::Item for AddressPoolPositions {
+    fn header() -> csv::Header {
+        vec!["address", "pool", "lp_amount", "assets", "usd", "weight"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        self.positions
+            .into_iter()
+            .map(|WeightedPosition { position, weight }| {
+                let assets: Vec<String> = position
+                    .assets
+                    .iter()
+                    .map(|asset| format!("{}{}", asset.amount, asset.denom))
+                    .collect();
+
+                vec![
+                    self.address.clone(),
+                    position.pool,
+                    position.lp_amount,
+                    assets.join(","),
+                    format!("{:.2}", position.usd),
+                    weight.map(|weight| weight.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect()
+    }
+}
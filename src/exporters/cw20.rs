@@ -0,0 +1,206 @@
+use async_trait::async_trait;
+use bigdecimal::{BigDecimal, Zero};
+use serde::Serialize;
+
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::{csv, queriers::cw20::Cw20Token, Context};
+
+use super::{average_ranking, item_activity, Exporter, Inspection};
+
+/// A token of the `--exporters` config file.
+struct Token {
+    name: String,
+    /// Key of the `ecosystem` activity ranking the balance.
+    activity: String,
+    cw20: Cw20Token,
+}
+
+pub struct Cw20 {
+    ctx: Arc<Context>,
+    csv: csv::Writer<AddressTokens>,
+    balances_csv: csv::Writer<AddressBalances>,
+    tokens: Vec<Token>,
+}
+
+impl Cw20 {
+    pub async fn create(ctx: Arc<Context>) -> Result<Self> {
+        let csv = ctx.exporter_csv_writer("cw20").await?;
+        let balances_csv = ctx.exporter_csv_writer("cw20-balances").await?;
+
+        let mut tokens = Vec::new();
+        for token in ctx.custom_exporters.cw20.iter() {
+            item_activity(&ctx, &token.activity, &format!("{} token", token.name))?;
+
+            let cw20 = Cw20Token::build(ctx.clone(), token.contract.clone()).await?;
+            tokens.push(Token {
+                name: token.name.clone(),
+                activity: token.activity.clone(),
+                cw20,
+            });
+        }
+        tracing::info!(count = tokens.len(), "cw20 tokens");
+
+        Ok(Self {
+            ctx,
+            csv,
+            balances_csv,
+            tokens,
+        })
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<(AddressTokens, AddressBalances)> {
+        let mut balances = Vec::new();
+        for token in self.tokens.iter() {
+            let balance = token
+                .cw20
+                .balance(owner.address.clone())
+                .await?
+                .with_scale(2);
+            let balance_score = balance.to_f64().ok_or(anyhow!(
+                "Failed to convert balance amount to f64: {}",
+                balance
+            ))?;
+            let activity =
+                item_activity(&self.ctx, &token.activity, &format!("{} token", token.name))?;
+
+            balances.push(Balance {
+                token: token.name.clone(),
+                contract: token.cw20.address().to_string(),
+                symbol: token.cw20.token_info().symbol.clone(),
+                balance,
+                weight: activity.weight,
+                ranking: activity.ranking(balance_score),
+            });
+        }
+
+        let score = average_ranking(
+            balances
+                .iter()
+                .map(|balance| (balance.ranking, balance.weight)),
+        );
+        let ranking =
+            self.rank(score as f64) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        let tokens = AddressTokens {
+            address: owner.address.clone(),
+            tokens: balances
+                .iter()
+                .filter(|balance| !balance.balance.is_zero())
+                .count(),
+            score: score as f64,
+            ranking,
+        };
+        let balances = AddressBalances {
+            address: owner.address.clone(),
+            balances,
+        };
+
+        Ok((tokens, balances))
+    }
+}
+
+#[async_trait]
+impl Exporter for Cw20 {
+    fn name(&self) -> &'static str {
+        "cw20"
+    }
+
+    #[tracing::instrument(name = "cw20::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting cw20 token balances");
+
+        let (tokens, balances) = self.collect(owner).await?;
+        self.csv.write(tokens).await?;
+        self.balances_csv.write(balances).await?;
+
+        tracing::info!("cw20 token balances export finished");
+
+        Ok(())
+    }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let (tokens, balances) = self.collect(owner).await?;
+        let weight = self.ctx.ranking.ecosystem.weight
+            * balances
+                .balances
+                .iter()
+                .map(|balance| balance.weight)
+                .sum::<f32>();
+
+        let data = serde_json::json!({
+            "tokens": tokens,
+            "balances": balances.balances,
+        });
+        Inspection::new(self.name(), tokens.score, tokens.ranking, weight, &data)
+    }
+
+    /// The score is already a ranking, see [`item_activity`].
+    fn rank(&self, score: f64) -> f32 {
+        score as f32
+    }
+}
+
+#[derive(Serialize)]
+pub struct AddressTokens {
+    address: String,
+    /// Configured tokens held by the address.
+    tokens: usize,
+    #[serde(skip)]
+    score: f64,
+    ranking: f32,
+}
+
+impl csv::Item for AddressTokens {
+    fn header() -> csv::Header {
+        vec!["address", "ranking", "tokens"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.address,
+            format!("{:.2}", self.ranking),
+            self.tokens.to_string(),
+        ]]
+    }
+}
+
+#[derive(Serialize)]
+struct Balance {
+    token: String,
+    contract: String,
+    symbol: String,
+    balance: BigDecimal,
+    /// Weight of the token activity in the `ecosystem` group.
+    weight: f32,
+    ranking: f32,
+}
+
+pub struct AddressBalances {
+    address: String,
+    balances: Vec<Balance>,
+}
+
+impl csv::Item for AddressBalances {
+    fn header() -> csv::Header {
+        vec![
+            "address", "token", "contract", "symbol", "balance", "ranking",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        self.balances
+            .into_iter()
+            .map(|balance| {
+                vec![
+                    self.address.clone(),
+                    balance.token,
+                    balance.contract,
+                    balance.symbol,
+                    balance.balance.to_string(),
+                    format!("{:.2}", balance.ranking),
+                ]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use serde::Serialize;
+
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::queriers::cw721::{Cw721Collection, Trait};
+use crate::{csv, Context};
+
+use super::{average_ranking, item_activity, Exporter, Inspection};
+
+/// A collection of the `--exporters` config file.
+struct Collection {
+    name: String,
+    /// Key of the `ecosystem` activity ranking the number of tokens.
+    activity: String,
+    traits: bool,
+    cw721: Cw721Collection,
+}
+
+pub struct Cw721 {
+    ctx: Arc<Context>,
+    csv: csv::Writer<AddressCollections>,
+    holdings_csv: csv::Writer<AddressHoldings>,
+    collections: Vec<Collection>,
+}
+
+impl Cw721 {
+    pub async fn create(ctx: Arc<Context>) -> Result<Self> {
+        let csv = ctx.exporter_csv_writer("cw721").await?;
+        let holdings_csv = ctx.exporter_csv_writer("cw721-holdings").await?;
+
+        let mut collections = Vec::new();
+        for collection in ctx.custom_exporters.cw721.iter() {
+            let item = format!("{} collection", collection.name);
+            item_activity(&ctx, &collection.activity, &item)?;
+
+            collections.push(Collection {
+                name: collection.name.clone(),
+                activity: collection.activity.clone(),
+                traits: collection.traits,
+                cw721: Cw721Collection::new(ctx.clone(), collection.contract.clone()),
+            });
+        }
+        tracing::info!(count = collections.len(), "cw721 collections");
+
+        Ok(Self {
+            ctx,
+            csv,
+            holdings_csv,
+            collections,
+        })
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<(AddressCollections, AddressHoldings)> {
+        let mut holdings = Vec::new();
+        for collection in self.collections.iter() {
+            let tokens = collection.cw721.tokens(&owner.address).await?;
+            let traits = if collection.traits {
+                self.traits(&collection.cw721, &tokens).await?
+            } else {
+                BTreeSet::new()
+            };
+
+            let item = format!("{} collection", collection.name);
+            let activity = item_activity(&self.ctx, &collection.activity, &item)?;
+
+            holdings.push(Holding {
+                collection: collection.name.clone(),
+                contract: collection.cw721.address().to_string(),
+                weight: activity.weight,
+                ranking: activity.ranking(tokens.len() as f64),
+                tokens,
+                traits,
+            });
+        }
+
+        let score = average_ranking(
+            holdings
+                .iter()
+                .map(|holding| (holding.ranking, holding.weight)),
+        );
+        let ranking =
+            self.rank(score as f64) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        let collections = AddressCollections {
+            address: owner.address.clone(),
+            collections: holdings
+                .iter()
+                .filter(|holding| !holding.tokens.is_empty())
+                .count(),
+            tokens: holdings.iter().map(|holding| holding.tokens.len()).sum(),
+            score: score as f64,
+            ranking,
+        };
+        let holdings = AddressHoldings {
+            address: owner.address.clone(),
+            holdings,
+        };
+
+        Ok((collections, holdings))
+    }
+
+    /// Distinct traits of the tokens, written as `trait_type:value`.
+    async fn traits(&self, cw721: &Cw721Collection, tokens: &[String]) -> Result<BTreeSet<String>> {
+        let traits: Vec<Vec<Trait>> = stream::iter(tokens.iter().cloned())
+            .map(|token_id| cw721.traits(token_id))
+            .buffer_unordered(10)
+            .try_collect()
+            .await?;
+
+        Ok(traits
+            .into_iter()
+            .flatten()
+            .map(|t| {
+                let value = match t.value {
+                    serde_json::Value::String(value) => value,
+                    value => value.to_string(),
+                };
+                format!("{}:{}", t.trait_type, value)
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Exporter for Cw721 {
+    fn name(&self) -> &'static str {
+        "cw721"
+    }
+
+    #[tracing::instrument(name = "cw721::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting cw721 holdings");
+
+        let (collections, holdings) = self.collect(owner).await?;
+        self.csv.write(collections).await?;
+        self.holdings_csv.write(holdings).await?;
+
+        tracing::info!("cw721 holdings export finished");
+
+        Ok(())
+    }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let (collections, holdings) = self.collect(owner).await?;
+        let weight = self.ctx.ranking.ecosystem.weight
+            * holdings
+                .holdings
+                .iter()
+                .map(|holding| holding.weight)
+                .sum::<f32>();
+
+        let data = serde_json::json!({
+            "collections": collections,
+            "holdings": holdings.holdings,
+        });
+        Inspection::new(
+            self.name(),
+            collections.score,
+            collections.ranking,
+            weight,
+            &data,
+        )
+    }
+
+    /// The score is already a ranking, see [`item_activity`].
+    fn rank(&self, score: f64) -> f32 {
+        score as f32
+    }
+}
+
+#[derive(Serialize)]
+pub struct AddressCollections {
+    address: String,
+    /// Configured collections the address holds tokens of.
+    collections: usize,
+    tokens: usize,
+    #[serde(skip)]
+    score: f64,
+    ranking: f32,
+}
+
+impl csv::Item for AddressCollections {
+    fn header() -> csv::Header {
+        vec!["address", "ranking", "collections", "tokens"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.address,
+            format!("{:.2}", self.ranking),
+            self.collections.to_string(),
+            self.tokens.to_string(),
+        ]]
+    }
+}
+
+#[derive(Serialize)]
+struct Holding {
+    collection: String,
+    contract: String,
+    tokens: Vec<String>,
+    /// Empty unless the traits are enabled for the collection.
+    traits: BTreeSet<String>,
+    /// Weight of the collection activity in the `ecosystem` group.
+    weight: f32,
+    ranking: f32,
+}
+
+pub struct AddressHoldings {
+    address: String,
+    holdings: Vec<Holding>,
+}
+
+impl csv::Item for AddressHoldings {
+    fn header() -> csv::Header {
+        vec![
+            "address",
+            "collection",
+            "contract",
+            "tokens",
+            "token_ids",
+            "traits",
+            "ranking",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        self.holdings
+            .into_iter()
+            .map(|holding| {
+                let list = |values: Vec<String>| {
+                    let values: Vec<String> = values.iter().map(|value| list_item(value)).collect();
+                    values.join(",")
+                };
+
+                vec![
+                    self.address.clone(),
+                    holding.collection,
+                    holding.contract,
+                    holding.tokens.len().to_string(),
+                    list(holding.tokens),
+                    list(holding.traits.into_iter().collect()),
+                    format!("{:.2}", holding.ranking),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Replaces the separators of the CSV and of its lists, and the line breaks,
+/// in a value coming from a contract.
+fn list_item(value: &str) -> String {
+    value.replace(['\n', '\r', ';', ','], " ")
+}
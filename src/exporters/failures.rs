@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use crate::{csv, participant::Participant};
+
+use super::Exporter;
+
+/// What to do when an exporter fails for an address.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorPolicy {
+    /// Stops the whole export on the first failure.
+    #[default]
+    Abort,
+    /// Records the failure and continues with the next address.
+    Skip,
+    /// Records the failure and retries it once the other addresses are exported.
+    RetryLater,
+}
+
+pub struct Failure<'a> {
+    pub exporter: &'a dyn Exporter,
+    pub owner: &'a Participant,
+    pub error: Error,
+    pub attempts: u32,
+}
+
+/// Writes the failures to `failures.csv` and logs a summary per exporter,
+/// returning the failure rate.
+pub async fn report(ctx: &Context, failures: Vec<FailedExport>, total: usize) -> Result<f64> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let csv = ctx.report_csv_writer("failures").await?;
+
+    let count = failures.len();
+    for failure in failures {
+        *counts.entry(failure.exporter).or_default() += 1;
+        csv.write(failure).await?;
+    }
+
+    for (exporter, failures) in counts.iter() {
+        tracing::warn!(exporter, failures, "exporter failed for some addresses");
+    }
+
+    let rate = match total {
+        0 => 0.0,
+        total => count as f64 / total as f64,
+    };
+    tracing::info!(count, total, rate, "export failures");
+
+    Ok(rate)
+}
+
+/// Returns an error when the failure rate exceeds the configured threshold.
+pub fn check_rate(ctx: &Context, rate: f64) -> Result<()> {
+    if rate > ctx.max_failure_rate {
+        return Err(anyhow!(
+            "failure rate {:.4} exceeds the threshold of {:.4}",
+            rate,
+            ctx.max_failure_rate
+        ));
+    }
+
+    Ok(())
+}
+
+pub struct FailedExport {
+    exporter: &'static str,
+    address: String,
+    error: String,
+    attempts: u32,
+}
+
+impl FailedExport {
+    pub fn new(exporter: &'static str, address: String, error: String, attempts: u32) -> Self {
+        Self {
+            exporter,
+            address,
+            error,
+            attempts,
+        }
+    }
+
+    pub fn exporter(&self) -> &'static str {
+        self.exporter
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl From<Failure<'_>> for FailedExport {
+    fn from(failure: Failure<'_>) -> Self {
+        Self::new(
+            failure.exporter.name(),
+            failure.owner.address.clone(),
+            format!("{:#}", failure.error),
+            failure.attempts,
+        )
+    }
+}
+
+impl csv::Item for FailedExport {
+    fn header() -> csv::Header {
+        vec!["exporter", "address", "error", "attempts"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.exporter.to_string(),
+            self.address,
+            self.error.replace(['\n', ';'], " "),
+            self.attempts.to_string(),
+        ]]
+    }
+}
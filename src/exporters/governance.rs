@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use serde::Serialize;
+use tendermint_rpc::endpoint::tx;
+
+use crate::clients::{Proposal, ProposalStatus, VoteOption};
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::{csv, Context};
+
+use super::{Exporter, Inspection};
+
+const VOTE_OPTIONS: [VoteOption; 4] = [
+    VoteOption::Yes,
+    VoteOption::Abstain,
+    VoteOption::No,
+    VoteOption::NoWithVeto,
+];
+
+pub struct Governance {
+    ctx: Arc<Context>,
+    csv: csv::Writer<AddressVotes>,
+    /// Proposals of the window that reached their voting period, oldest first.
+    proposals: Vec<Proposal>,
+}
+
+impl Governance {
+    pub async fn create(ctx: Arc<Context>) -> Result<Self> {
+        let csv = ctx.exporter_csv_writer("governance").await?;
+
+        let mut proposals: Vec<Proposal> = ctx
+            .cosmos
+            .gov
+            .proposals()
+            .await?
+            .into_iter()
+            .filter(|proposal| {
+                !matches!(
+                    proposal.status,
+                    ProposalStatus::Unspecified | ProposalStatus::DepositPeriod
+                )
+            })
+            .collect();
+        proposals.sort_by_key(|proposal| proposal.id);
+
+        if let Some(activity) = &ctx.ranking.archway.activities.governance {
+            if let Some(since) = activity.since {
+                proposals.retain(|proposal| proposal.voting_start_time >= Some(since));
+            }
+            if let Some(last) = activity.last_proposals {
+                let skip = proposals.len().saturating_sub(last);
+                proposals.drain(..skip);
+            }
+        }
+        tracing::info!(count = proposals.len(), "governance proposals in window");
+
+        Ok(Self {
+            ctx,
+            csv,
+            proposals,
+        })
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<AddressVotes> {
+        let address = owner.address.as_str();
+
+        // votes are removed from the state once tallied, so past ones come from the
+        // transactions, where the last vote on a proposal replaces the previous ones;
+        // the whole history is searched since the window is set by the proposals,
+        // which can be voted on long before `since_height`
+        let mut votes: HashMap<u64, Vec<VoteOption>> = self
+            .ctx
+            .cosmos
+            .tx
+            .votes(address, None)
+            .try_fold(HashMap::new(), |mut votes, tx| {
+                votes.extend(tx_votes(&tx));
+                future::ready(Ok(votes))
+            })
+            .await?;
+
+        for proposal in self.proposals.iter() {
+            if proposal.status != ProposalStatus::VotingPeriod {
+                continue;
+            }
+
+            if let Some(vote) = self.ctx.cosmos.gov.vote(proposal, address).await? {
+                let options = vote.options.into_iter().map(|(option, _)| option);
+                votes.insert(proposal.id, options.collect());
+            }
+        }
+
+        let mut options: HashMap<VoteOption, usize> = HashMap::new();
+        let mut voted = 0;
+        for proposal in self.proposals.iter() {
+            let Some(vote) = votes.get(&proposal.id) else {
+                continue;
+            };
+
+            voted += 1;
+            for option in vote {
+                *options.entry(*option).or_default() += 1;
+            }
+        }
+
+        let participation = match self.proposals.len() {
+            0 => 0.0,
+            total => voted as f64 / total as f64 * 100.0,
+        };
+        tracing::debug!(voted, participation, "governance votes");
+
+        let ranking =
+            self.rank(participation) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+        let count = |option: VoteOption| options.get(&option).copied().unwrap_or_default();
+
+        Ok(AddressVotes {
+            address: owner.address.clone(),
+            proposals: self.proposals.len(),
+            voted,
+            participation,
+            yes: count(VoteOption::Yes),
+            no: count(VoteOption::No),
+            abstain: count(VoteOption::Abstain),
+            no_with_veto: count(VoteOption::NoWithVeto),
+            ranking,
+        })
+    }
+}
+
+#[async_trait]
+impl Exporter for Governance {
+    fn name(&self) -> &'static str {
+        "governance"
+    }
+
+    #[tracing::instrument(name = "governance::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting governance votes");
+
+        let votes = self.collect(owner).await?;
+        self.csv.write(votes).await?;
+
+        tracing::info!("governance votes export finished");
+
+        Ok(())
+    }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let votes = self.collect(owner).await?;
+        let archway = &self.ctx.ranking.archway;
+        let weight = archway
+            .activities
+            .governance
+            .as_ref()
+            .map(|activity| archway.activity_weight(&activity.activity))
+            .unwrap_or_default();
+
+        Inspection::new(
+            self.name(),
+            votes.participation,
+            votes.ranking,
+            weight,
+            &votes,
+        )
+    }
+
+    /// Addresses are not ranked on their votes unless the activity is configured.
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx
+            .ranking
+            .archway
+            .activities
+            .governance
+            .as_ref()
+            .map(|activity| activity.ranking(score))
+            .unwrap_or_default()
+    }
+}
+
+/// Options of the votes cast in a transaction, keyed by proposal.
+fn tx_votes(tx: &tx::Response) -> Vec<(u64, Vec<VoteOption>)> {
+    tx.tx_result
+        .events
+        .iter()
+        .filter(|event| event.kind == "proposal_vote")
+        .filter_map(|event| {
+            let attribute = |key: &str| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|attr| attr.key == key)
+                    .map(|attr| attr.value.as_str())
+            };
+
+            let proposal_id = attribute("proposal_id")?.parse().ok()?;
+            let options = parse_options(attribute("option")?);
+            Some((proposal_id, options))
+        })
+        .collect()
+}
+
+/// Parses the options of a `proposal_vote` event, written as
+/// `option:VOTE_OPTION_YES weight:"1.0"` or `[{"option":1,"weight":"1.0"}]`
+/// depending on the Cosmos SDK version.
+fn parse_options(value: &str) -> Vec<VoteOption> {
+    let named: Vec<VoteOption> = value
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter_map(|name| {
+            VOTE_OPTIONS
+                .into_iter()
+                .find(|option| option.as_str_name() == name)
+        })
+        .collect();
+    if !named.is_empty() {
+        return named;
+    }
+
+    let options: Vec<serde_json::Value> = serde_json::from_str(value).unwrap_or_default();
+    options
+        .iter()
+        .filter_map(|option| option.get("option")?.as_i64())
+        .filter_map(|option| VoteOption::try_from(option as i32).ok())
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct AddressVotes {
+    address: String,
+    /// Proposals in the window.
+    proposals: usize,
+    voted: usize,
+    /// Percentage of the proposals voted on.
+    participation: f64,
+    yes: usize,
+    no: usize,
+    abstain: usize,
+    no_with_veto: usize,
+    ranking: f32,
+}
+
+impl csv::Item for AddressVotes {
+    fn header() -> csv::Header {
+        vec![
+            "address",
+            "ranking",
+            "proposals",
+            "voted",
+            "participation",
+            "yes",
+            "no",
+            "abstain",
+            "no_with_veto",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.address,
+            format!("{:.2}", self.ranking),
+            self.proposals.to_string(),
+            self.voted.to_string(),
+            format!("{:.2}", self.participation),
+            self.yes.to_string(),
+            self.no.to_string(),
+            self.abstain.to_string(),
+            self.no_with_veto.to_string(),
+        ]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_options() {
+        // written by the SDK 0.47 with the `String` of the weighted options
+        let options = parse_options(r#"option:VOTE_OPTION_YES weight:"1.000000000000000000""#);
+        assert_eq!(options, vec![VoteOption::Yes]);
+
+        let options = parse_options(
+            "option:VOTE_OPTION_NO_WITH_VETO weight:\"0.6\" \
+             option:VOTE_OPTION_ABSTAIN weight:\"0.4\"",
+        );
+        assert_eq!(options, vec![VoteOption::NoWithVeto, VoteOption::Abstain]);
+    }
+
+    #[test]
+    fn parses_weighted_json_options() {
+        let options = parse_options(r#"[{"option":1,"weight":"1.000000000000000000"}]"#);
+        assert_eq!(options, vec![VoteOption::Yes]);
+
+        let options = parse_options(r#"[{"option":3,"weight":"0.5"},{"option":4,"weight":"0.5"}]"#);
+        assert_eq!(options, vec![VoteOption::No, VoteOption::NoWithVeto]);
+    }
+
+    #[test]
+    fn ignores_unknown_options() {
+        assert!(parse_options("").is_empty());
+        assert!(parse_options("option:VOTE_OPTION_MAYBE").is_empty());
+        assert!(parse_options(r#"[{"option":9,"weight":"1"}]"#).is_empty());
+    }
+}
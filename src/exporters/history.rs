@@ -0,0 +1,329 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use futures::prelude::*;
+
+use crate::block::BlockId;
+use crate::csv;
+use crate::manifest::Manifest;
+use crate::metadata::Metadata;
+use crate::participant::{self, Participant};
+use crate::prelude::*;
+
+use super::failures::{self, FailedExport};
+use super::{ErrorPolicy, Exporter, ExporterKind};
+
+/// How the scores of an activity at each height are combined before ranking.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Aggregation {
+    #[default]
+    Mean,
+    Median,
+    Min,
+    /// Mean weighted by the time between heights.
+    TimeWeighted,
+}
+
+impl Aggregation {
+    /// Combines the samples, which must be sorted by time.
+    fn aggregate(&self, samples: &[Sample]) -> f64 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+
+        let scores = || samples.iter().map(|sample| sample.score);
+        let mean = scores().sum::<f64>() / samples.len() as f64;
+
+        match self {
+            Self::Mean => mean,
+            Self::Median => {
+                let mut scores: Vec<f64> = scores().collect();
+                scores.sort_by(f64::total_cmp);
+
+                let mid = scores.len() / 2;
+                if scores.len().is_multiple_of(2) {
+                    (scores[mid - 1] + scores[mid]) / 2.0
+                } else {
+                    scores[mid]
+                }
+            }
+            Self::Min => scores().fold(f64::INFINITY, f64::min),
+            Self::TimeWeighted => {
+                // trapezoidal integral of the scores over the window
+                let (area, duration) =
+                    samples.windows(2).fold((0.0, 0.0), |(area, duration), w| {
+                        let seconds = (w[1].time - w[0].time).num_seconds() as f64;
+                        let area = area + (w[0].score + w[1].score) / 2.0 * seconds;
+                        (area, duration + seconds)
+                    });
+
+                if duration > 0.0 {
+                    area / duration
+                } else {
+                    mean
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Sample {
+    time: DateTime<Utc>,
+    score: f64,
+}
+
+/// Runs the exporters at each of the blocks, writing the scores at every height
+/// to `<exporter>-history.csv` and the rankings of the aggregated scores to
+/// `<exporter>-aggregated.csv`.
+pub async fn run(ctx: Arc<Context>, blocks: &[BlockId], aggregation: Aggregation) -> Result<()> {
+    tracing::info!(
+        heights = blocks.len(),
+        ?aggregation,
+        "starting history export"
+    );
+
+    ctx.create_output_folder()?;
+    let metadata = Metadata::new(&ctx);
+    metadata.write(&ctx).await?;
+
+    if ctx.exporters.contains(&ExporterKind::Astrovault) {
+        tracing::warn!(
+            "the Astrovault API is not historical, its latest values are used at every height"
+        );
+    }
+    if ctx.exporters.iter().any(ExporterKind::needs_prices) {
+        tracing::warn!("CoinGecko prices are the current ones at every height");
+    }
+
+    let participants = participant::all(ctx.clone()).await?;
+    tracing::info!(count = participants.len(), "total participants");
+
+    let mut history_csv = HashMap::new();
+    for kind in ctx.exporters.iter() {
+        let csv: csv::Writer<HistoryRecord> =
+            ctx.csv_writer(&format!("{}-history", kind.name())).await?;
+        history_csv.insert(kind.name(), csv);
+    }
+
+    let mut samples: HashMap<(&'static str, &str), Vec<Sample>> = HashMap::new();
+    let mut failed: Vec<FailedExport> = Vec::new();
+    let mut exporters: Vec<Box<dyn Exporter>> = Vec::new();
+
+    for block in blocks {
+        let snapshot = Arc::new(ctx.at(*block).await?);
+        let (height, time) = (
+            snapshot.cosmos.block.height.value(),
+            snapshot.cosmos.block.time,
+        );
+        tracing::info!(height, %time, "exporting block");
+
+        exporters.clear();
+        for kind in ctx.exporters.iter() {
+            let exporter = kind.create(snapshot.clone()).await?;
+            exporter.prepare(&participants).await?;
+            exporters.push(exporter);
+        }
+
+        for exporter in exporters.iter() {
+            let results: Vec<(&Participant, Result<_>)> = stream::iter(participants.iter())
+                .map(
+                    |participant| async move { (participant, exporter.inspect(participant).await) },
+                )
+                .buffer_unordered(32)
+                .collect()
+                .await;
+
+            for (participant, result) in results {
+                let inspection = match result {
+                    Err(err) if ctx.on_error == ErrorPolicy::Abort => return Err(err),
+                    Err(err) => {
+                        tracing::warn!(
+                            exporter = exporter.name(),
+                            address = participant.address,
+                            height,
+                            "export failed: {:#}",
+                            err
+                        );
+                        failed.push(FailedExport::new(
+                            exporter.name(),
+                            participant.address.clone(),
+                            format!("at height {}: {:#}", height, err),
+                            1,
+                        ));
+                        continue;
+                    }
+                    inspection => inspection?,
+                };
+
+                history_csv[exporter.name()]
+                    .write(HistoryRecord {
+                        address: participant.address.clone(),
+                        height,
+                        time,
+                        score: inspection.score,
+                        ranking: inspection.ranking,
+                    })
+                    .await?;
+
+                samples
+                    .entry((exporter.name(), participant.address.as_str()))
+                    .or_default()
+                    .push(Sample {
+                        time,
+                        score: inspection.score,
+                    });
+            }
+        }
+    }
+
+    // the scores missing a height can't be aggregated like the others,
+    // so those addresses are only reported as failures
+    let incomplete: HashSet<(&'static str, &str)> = failed
+        .iter()
+        .map(|failure| (failure.exporter(), failure.address()))
+        .collect();
+
+    // every block shares the ranking config, so the last exporters rank the aggregated scores
+    for exporter in exporters.iter() {
+        let csv = ctx
+            .csv_writer(&format!("{}-aggregated", exporter.name()))
+            .await?;
+
+        for participant in participants.iter() {
+            if incomplete.contains(&(exporter.name(), participant.address.as_str())) {
+                tracing::warn!(
+                    exporter = exporter.name(),
+                    address = participant.address,
+                    "missing scores at some heights, not aggregating"
+                );
+                continue;
+            }
+
+            let mut samples = samples
+                .remove(&(exporter.name(), participant.address.as_str()))
+                .unwrap_or_default();
+            samples.sort_by_key(|sample| sample.time);
+
+            let score = aggregation.aggregate(&samples);
+            csv.write(AggregatedRecord {
+                address: participant.address.clone(),
+                ranking: exporter.rank(score)
+                    * ctx.ranking.sybil_factor(participant.sybil_confidence),
+                score,
+                samples: samples.len(),
+            })
+            .await?;
+        }
+    }
+
+    let total = blocks.len() * participants.len() * exporters.len();
+    let rate = failures::report(&ctx, failed, total).await?;
+
+    // the CSV files are only complete once the writers are released
+    drop(exporters);
+    drop(history_csv);
+    ctx.flush_csv_writers().await?;
+    Manifest::new(&ctx, metadata, |kind| format!("{}-aggregated", kind.name()))?
+        .write(ctx.output()?)
+        .await?;
+
+    failures::check_rate(&ctx, rate)?;
+
+    tracing::info!("history export finished");
+
+    Ok(())
+}
+
+pub struct HistoryRecord {
+    address: String,
+    height: u64,
+    time: DateTime<Utc>,
+    score: f64,
+    ranking: f32,
+}
+
+impl csv::Item for HistoryRecord {
+    fn header() -> csv::Header {
+        vec!["address", "height", "time", "score", "ranking"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.address,
+            self.height.to_string(),
+            self.time.to_rfc3339(),
+            format!("{:.2}", self.score),
+            format!("{:.2}", self.ranking),
+        ]]
+    }
+}
+
+pub struct AggregatedRecord {
+    address: String,
+    ranking: f32,
+    score: f64,
+    samples: usize,
+}
+
+impl csv::Item for AggregatedRecord {
+    fn header() -> csv::Header {
+        vec!["address", "ranking", "score", "samples"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.address,
+            format!("{:.2}", self.ranking),
+            format!("{:.2}", self.score),
+            self.samples.to_string(),
+        ]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    /// Samples at the given seconds from the start of the window.
+    fn samples(points: &[(i64, f64)]) -> Vec<Sample> {
+        let start = DateTime::<Utc>::UNIX_EPOCH;
+        points
+            .iter()
+            .map(|&(seconds, score)| Sample {
+                time: start + TimeDelta::seconds(seconds),
+                score,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn median_of_odd_count() {
+        let samples = samples(&[(0, 7.0), (1, 1.0), (2, 3.0)]);
+        assert_eq!(Aggregation::Median.aggregate(&samples), 3.0);
+    }
+
+    #[test]
+    fn median_of_even_count() {
+        let samples = samples(&[(0, 8.0), (1, 1.0), (2, 4.0), (3, 2.0)]);
+        assert_eq!(Aggregation::Median.aggregate(&samples), 3.0);
+    }
+
+    #[test]
+    fn time_weighted_with_uneven_gaps() {
+        // 10 seconds ramping from 0 to 10, then 90 seconds at 10
+        let samples = samples(&[(0, 0.0), (10, 10.0), (100, 10.0)]);
+
+        assert_eq!(Aggregation::TimeWeighted.aggregate(&samples), 9.5);
+        assert!((Aggregation::Mean.aggregate(&samples) - 20.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn time_weighted_without_duration_is_mean() {
+        let samples = samples(&[(0, 2.0), (0, 4.0)]);
+        assert_eq!(Aggregation::TimeWeighted.aggregate(&samples), 3.0);
+        assert_eq!(Aggregation::TimeWeighted.aggregate(&[]), 0.0);
+    }
+}
@@ -1,23 +1,32 @@
-use std::collections::HashMap;
-
 use async_trait::async_trait;
+use serde::Serialize;
 
+use crate::clients::CoinGeckoClient;
 use crate::coin::Coin;
+use crate::participant::Participant;
 use crate::prelude::*;
-use crate::queriers::soulbound::TokenInfo;
 use crate::{csv, Context};
 
-use super::Exporter;
+use super::{Exporter, Inspection};
 
 pub struct Ibc {
     ctx: Arc<Context>,
+    coingecko: Arc<CoinGeckoClient>,
     csv: csv::Writer<AddressBalances>,
 }
 
 impl Ibc {
     pub async fn create(ctx: Arc<Context>) -> Result<Self> {
-        let csv = ctx.csv_writer("ibc").await?;
-        Ok(Self { ctx, csv })
+        let coingecko = ctx
+            .coingecko
+            .clone()
+            .ok_or(anyhow!("missing coingecko client"))?;
+        let csv = ctx.exporter_csv_writer("ibc").await?;
+        Ok(Self {
+            ctx,
+            coingecko,
+            csv,
+        })
     }
 
     async fn calculate_balances(&self, address: String) -> Result<Vec<Balance>> {
@@ -34,7 +43,7 @@ impl Ibc {
             .iter()
             .flat_map(|coin| coin.coingecko_id.as_deref())
             .collect();
-        let prices = self.ctx.coingecko.price(coingecko_ids).await?;
+        let prices = self.coingecko.price(coingecko_ids);
         let balances = coins
             .into_iter()
             .flat_map(|coin| {
@@ -42,6 +51,7 @@ impl Ibc {
                     prices.get(id).map(|price| {
                         coin.total_value(price.usd).map(|total_value| Balance {
                             balance: coin.clone(),
+                            price: price.usd,
                             total_value,
                         })
                     })
@@ -53,39 +63,60 @@ impl Ibc {
 
         Ok(balances)
     }
-}
-
-#[async_trait]
-impl Exporter for Ibc {
-    #[tracing::instrument(name = "balances::export", skip_all, fields(address = token.owner))]
-    async fn export(&self, token: &TokenInfo) -> Result<()> {
-        tracing::info!("exporting all token balances");
 
-        let balances = self.calculate_balances(token.owner.clone()).await?;
+    async fn collect(&self, owner: &Participant) -> Result<AddressBalances> {
+        let balances = self.calculate_balances(owner.address.clone()).await?;
         let usd = balances.iter().map(|balance| balance.total_value).sum();
-        let ranking = self.ctx.ranking.archway.activities.ibc.ranking(usd);
+        let ranking = self.rank(usd) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
 
-        let assets = AddressBalances {
-            address: token.owner.clone(),
+        Ok(AddressBalances {
+            address: owner.address.clone(),
             balances,
             usd,
             ranking,
-        };
+        })
+    }
+}
 
+#[async_trait]
+impl Exporter for Ibc {
+    fn name(&self) -> &'static str {
+        "ibc"
+    }
+
+    #[tracing::instrument(name = "balances::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting all token balances");
+
+        let assets = self.collect(owner).await?;
         self.csv.write(assets).await?;
 
         tracing::info!("token balances export finished");
 
         Ok(())
     }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let assets = self.collect(owner).await?;
+        let archway = &self.ctx.ranking.archway;
+        let weight = archway.activity_weight(&archway.activities.ibc);
+
+        Inspection::new(self.name(), assets.usd, assets.ranking, weight, &assets)
+    }
+
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx.ranking.archway.activities.ibc.ranking(score)
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 struct Balance {
     pub balance: Coin,
+    pub price: f64,
     pub total_value: f64,
 }
 
+#[derive(Serialize)]
 pub struct AddressBalances {
     address: String,
     balances: Vec<Balance>,
@@ -0,0 +1,526 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tendermint_rpc::endpoint::tx;
+use tokio::sync::Mutex;
+
+use crate::clients::CoinGeckoClient;
+use crate::coin::Coin;
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::{csv, Context};
+
+use super::{Exporter, Inspection};
+
+const TRANSFER_PORT: &str = "transfer";
+
+pub struct IbcTransfers {
+    ctx: Arc<Context>,
+    coingecko: Arc<CoinGeckoClient>,
+    csv: csv::Writer<AddressTransfers>,
+    volumes_csv: csv::Writer<AddressVolumes>,
+    /// Chain ids of the channels already queried.
+    chains: Mutex<HashMap<String, String>>,
+}
+
+impl IbcTransfers {
+    pub async fn create(ctx: Arc<Context>) -> Result<Self> {
+        let coingecko = ctx
+            .coingecko
+            .clone()
+            .ok_or(anyhow!("missing coingecko client"))?;
+        let csv = ctx.exporter_csv_writer("ibc-transfers").await?;
+        let volumes_csv = ctx.exporter_csv_writer("ibc-transfers-volumes").await?;
+
+        Ok(Self {
+            ctx,
+            coingecko,
+            csv,
+            volumes_csv,
+            chains: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<(AddressTransfers, AddressVolumes)> {
+        let address = owner.address.as_str();
+        let tx = &self.ctx.cosmos.tx;
+        // only the packets of each transaction are kept while paging through them
+        let (mut packets, received) = futures::try_join!(
+            tx.ibc_sent(address, self.ctx.since_height)
+                .map_ok(|tx| outbound_packets(&tx, address))
+                .try_concat(),
+            tx.ibc_received(address, self.ctx.since_height)
+                .map_ok(|tx| inbound_packets(&tx, address))
+                .try_concat()
+        )?;
+        packets.extend(received);
+
+        let mut volumes: BTreeMap<(Direction, String, String), Volume> = BTreeMap::new();
+        for packet in packets.iter() {
+            let chain = self.chain_id(&packet.channel).await?;
+            let coin = self.price(packet)?;
+
+            let volume = volumes
+                .entry((packet.direction, chain.clone(), coin.denom.clone()))
+                .or_insert_with(|| Volume {
+                    direction: packet.direction,
+                    chain,
+                    denom: coin.denom.clone(),
+                    amount: BigDecimal::default(),
+                    usd: 0.0,
+                    transfers: 0,
+                });
+            volume.amount += &coin.amount;
+            volume.usd += coin.usd;
+            volume.transfers += 1;
+        }
+
+        let volumes: Vec<Volume> = volumes.into_values().collect();
+        let usd = |direction: Direction| {
+            volumes
+                .iter()
+                .filter(|volume| volume.direction == direction)
+                .map(|volume| volume.usd)
+                .sum::<f64>()
+        };
+        let (inbound_usd, outbound_usd) = (usd(Direction::Inbound), usd(Direction::Outbound));
+        tracing::debug!(
+            inbound_usd,
+            outbound_usd,
+            transfers = packets.len(),
+            "IBC transfers"
+        );
+
+        let ranking =
+            self.rank(inbound_usd) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        let transfers = AddressTransfers {
+            address: owner.address.clone(),
+            inbound_usd,
+            outbound_usd,
+            transfers: packets.len(),
+            ranking,
+        };
+        let volumes = AddressVolumes {
+            address: owner.address.clone(),
+            volumes,
+        };
+
+        Ok((transfers, volumes))
+    }
+
+    /// Id of the chain at the other end of an Archway channel,
+    /// or the channel itself when it's unknown.
+    async fn chain_id(&self, channel: &str) -> Result<String> {
+        if let Some(chain) = self.chains.lock().await.get(channel) {
+            return Ok(chain.clone());
+        }
+
+        let chain = self
+            .ctx
+            .cosmos
+            .ibc
+            .counterparty_chain_id(TRANSFER_PORT, channel)
+            .await?
+            .unwrap_or_else(|| channel.to_string());
+        self.chains
+            .lock()
+            .await
+            .insert(channel.to_string(), chain.clone());
+
+        Ok(chain)
+    }
+
+    /// Values the amount of a packet, which is left unpriced when
+    /// its denom is missing from the token map.
+    fn price(&self, packet: &Packet) -> Result<PricedCoin> {
+        let Some(info) = self.ctx.token_map.get(&packet.denom) else {
+            return Ok(PricedCoin {
+                denom: packet.denom.clone(),
+                amount: packet.amount.parse()?,
+                usd: 0.0,
+            });
+        };
+
+        let coin = Coin::build(
+            info.denom.clone(),
+            packet.amount.clone(),
+            info.decimals,
+            info.coingecko_id.clone(),
+        )?;
+        let usd = match coin.coingecko_id.as_deref() {
+            Some(id) => match self.coingecko.price(vec![id]).get(id) {
+                Some(price) => coin.total_value(price.usd)?,
+                None => 0.0,
+            },
+            None => 0.0,
+        };
+
+        Ok(PricedCoin {
+            denom: coin.denom,
+            amount: coin.amount,
+            usd,
+        })
+    }
+}
+
+#[async_trait]
+impl Exporter for IbcTransfers {
+    fn name(&self) -> &'static str {
+        "ibc-transfers"
+    }
+
+    #[tracing::instrument(name = "ibc_transfers::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting IBC transfers");
+
+        let (transfers, volumes) = self.collect(owner).await?;
+        self.csv.write(transfers).await?;
+        self.volumes_csv.write(volumes).await?;
+
+        tracing::info!("IBC transfers export finished");
+
+        Ok(())
+    }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let (transfers, volumes) = self.collect(owner).await?;
+        let archway = &self.ctx.ranking.archway;
+        let weight = archway
+            .activities
+            .ibc_transfers
+            .as_ref()
+            .map(|activity| archway.activity_weight(activity))
+            .unwrap_or_default();
+
+        let data = serde_json::json!({
+            "transfers": transfers,
+            "volumes": volumes.volumes,
+        });
+        Inspection::new(
+            self.name(),
+            transfers.inbound_usd,
+            transfers.ranking,
+            weight,
+            &data,
+        )
+    }
+
+    /// Addresses are not ranked on their IBC transfers unless the activity is configured.
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx
+            .ranking
+            .archway
+            .activities
+            .ibc_transfers
+            .as_ref()
+            .map(|activity| activity.ranking(score))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Inbound => "inbound",
+            Self::Outbound => "outbound",
+        }
+    }
+}
+
+/// Data of an ICS-20 transfer packet.
+#[derive(Debug, Deserialize)]
+struct PacketData {
+    denom: String,
+    amount: String,
+    sender: String,
+    receiver: String,
+}
+
+/// A transfer of the address, with the denom it has on Archway.
+#[derive(Debug)]
+struct Packet {
+    direction: Direction,
+    /// Archway end of the channel.
+    channel: String,
+    denom: String,
+    amount: String,
+}
+
+struct PricedCoin {
+    denom: String,
+    amount: BigDecimal,
+    usd: f64,
+}
+
+/// Attributes of the events of a kind, keyed by name.
+fn events<'a>(
+    tx: &'a tx::Response,
+    kind: &'a str,
+) -> impl Iterator<Item = HashMap<&'a str, &'a str>> {
+    tx.tx_result
+        .events
+        .iter()
+        .filter(move |event| event.kind == kind)
+        .map(|event| {
+            event
+                .attributes
+                .iter()
+                .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+                .collect()
+        })
+}
+
+fn packet_data(attributes: &HashMap<&str, &str>) -> Option<PacketData> {
+    let data = attributes.get("packet_data")?;
+    serde_json::from_str(data).ok()
+}
+
+/// Transfers sent by the address, whose denom is the full trace of the token.
+fn outbound_packets(tx: &tx::Response, address: &str) -> Vec<Packet> {
+    events(tx, "send_packet")
+        .filter(|attributes| attributes.get("packet_src_port") == Some(&TRANSFER_PORT))
+        .filter_map(|attributes| {
+            let data = packet_data(&attributes).filter(|data| data.sender == address)?;
+            Some(Packet {
+                direction: Direction::Outbound,
+                channel: attributes.get("packet_src_channel")?.to_string(),
+                denom: local_denom(&data.denom),
+                amount: data.amount,
+            })
+        })
+        .collect()
+}
+
+/// Transfers received by the address, skipping the failed ones.
+fn inbound_packets(tx: &tx::Response, address: &str) -> Vec<Packet> {
+    let failed = events(tx, "fungible_token_packet").any(|attributes| {
+        attributes.get("receiver") == Some(&address) && attributes.get("success") != Some(&"true")
+    });
+    if failed {
+        return vec![];
+    }
+
+    events(tx, "recv_packet")
+        .filter(|attributes| attributes.get("packet_dst_port") == Some(&TRANSFER_PORT))
+        .filter_map(|attributes| {
+            let data = packet_data(&attributes).filter(|data| data.receiver == address)?;
+            let source = format!(
+                "{}/{}/",
+                attributes.get("packet_src_port")?,
+                attributes.get("packet_src_channel")?
+            );
+            let channel = attributes.get("packet_dst_channel")?.to_string();
+
+            // tokens coming back lose the hop they took when leaving Archway,
+            // while the others gain the hop of the receiving channel
+            let trace = match data.denom.strip_prefix(&source) {
+                Some(trace) => trace.to_string(),
+                None => format!("{}/{}/{}", TRANSFER_PORT, channel, data.denom),
+            };
+
+            Some(Packet {
+                direction: Direction::Inbound,
+                channel,
+                denom: local_denom(&trace),
+                amount: data.amount,
+            })
+        })
+        .collect()
+}
+
+/// Denom of a token on Archway from its trace, which is hashed for IBC tokens.
+fn local_denom(trace: &str) -> String {
+    if !trace.contains('/') {
+        return trace.to_string();
+    }
+
+    format!("ibc/{}", hex::encode_upper(Sha256::digest(trace)))
+}
+
+#[derive(Serialize)]
+pub struct AddressTransfers {
+    address: String,
+    inbound_usd: f64,
+    outbound_usd: f64,
+    transfers: usize,
+    ranking: f32,
+}
+
+impl csv::Item for AddressTransfers {
+    fn header() -> csv::Header {
+        vec![
+            "address",
+            "ranking",
+            "inbound_usd",
+            "outbound_usd",
+            "transfers",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.address,
+            format!("{:.2}", self.ranking),
+            format!("{:.2}", self.inbound_usd),
+            format!("{:.2}", self.outbound_usd),
+            self.transfers.to_string(),
+        ]]
+    }
+}
+
+/// Volume of the transfers of a denom in one direction with a chain.
+#[derive(Serialize)]
+struct Volume {
+    direction: Direction,
+    chain: String,
+    denom: String,
+    amount: BigDecimal,
+    usd: f64,
+    transfers: usize,
+}
+
+pub struct AddressVolumes {
+    address: String,
+    volumes: Vec<Volume>,
+}
+
+impl csv::Item for AddressVolumes {
+    fn header() -> csv::Header {
+        vec![
+            "address",
+            "direction",
+            "chain",
+            "denom",
+            "amount",
+            "usd",
+            "transfers",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        self.volumes
+            .into_iter()
+            .map(|volume| {
+                vec![
+                    self.address.clone(),
+                    volume.direction.name().to_string(),
+                    volume.chain,
+                    volume.denom,
+                    volume.amount.to_string(),
+                    format!("{:.2}", volume.usd),
+                    volume.transfers.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tendermint::abci::{types::ExecTxResult, Event};
+    use tendermint::Hash;
+
+    use super::*;
+
+    const RECEIVER: &str = "archway1receiver";
+
+    /// Hash of `transfer/channel-0/uatom`, the ATOM denom on its first channel.
+    const ATOM_DENOM: &str = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+    fn recv_packet(denom: &str, src_channel: &str, dst_channel: &str) -> tx::Response {
+        let data = serde_json::json!({
+            "denom": denom,
+            "amount": "100",
+            "sender": "cosmos1sender",
+            "receiver": RECEIVER,
+        });
+        let events = vec![
+            Event::new(
+                "recv_packet",
+                [
+                    ("packet_data", data.to_string()),
+                    ("packet_src_port", TRANSFER_PORT.to_string()),
+                    ("packet_src_channel", src_channel.to_string()),
+                    ("packet_dst_port", TRANSFER_PORT.to_string()),
+                    ("packet_dst_channel", dst_channel.to_string()),
+                ],
+            ),
+            Event::new(
+                "fungible_token_packet",
+                [("receiver", RECEIVER), ("success", "true")],
+            ),
+        ];
+
+        tx::Response {
+            hash: Hash::None,
+            height: Default::default(),
+            index: 0,
+            tx_result: ExecTxResult {
+                events,
+                ..Default::default()
+            },
+            tx: vec![],
+            proof: None,
+        }
+    }
+
+    #[test]
+    fn keeps_native_denom() {
+        assert_eq!(local_denom("aarch"), "aarch");
+    }
+
+    #[test]
+    fn hashes_trace() {
+        assert_eq!(local_denom("transfer/channel-0/uatom"), ATOM_DENOM);
+    }
+
+    #[test]
+    fn hashes_foreign_token_with_receiving_hop() {
+        let packets = inbound_packets(&recv_packet("uatom", "channel-141", "channel-0"), RECEIVER);
+
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].channel, "channel-0");
+        assert_eq!(packets[0].denom, ATOM_DENOM);
+    }
+
+    #[test]
+    fn unwinds_returning_token() {
+        // `aarch` sent from Archway on channel-0 comes back with the hop of
+        // the counterparty channel, here channel-141
+        let tx = recv_packet("transfer/channel-141/aarch", "channel-141", "channel-0");
+        let packets = inbound_packets(&tx, RECEIVER);
+
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].denom, "aarch");
+    }
+
+    #[test]
+    fn unwinds_one_hop_of_multi_hop_token() {
+        let tx = recv_packet(
+            "transfer/channel-141/transfer/channel-5/uosmo",
+            "channel-141",
+            "channel-0",
+        );
+        let packets = inbound_packets(&tx, RECEIVER);
+
+        assert_eq!(packets[0].denom, local_denom("transfer/channel-5/uosmo"));
+    }
+
+    #[test]
+    fn skips_other_receivers() {
+        let tx = recv_packet("uatom", "channel-141", "channel-0");
+        assert!(inbound_packets(&tx, "archway1other").is_empty());
+    }
+}
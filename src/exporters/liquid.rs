@@ -1,80 +1,165 @@
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
+use serde::Serialize;
 
+use crate::participant::Participant;
 use crate::prelude::*;
-use crate::queriers::soulbound::TokenInfo;
-use crate::{csv, queriers::liquid::LiquidFinanceCw20, Context};
+use crate::queriers::{cw20::Cw20Token, liquid::LiquidFinanceStaking};
+use crate::{csv, Context};
 
-use super::Exporter;
+use super::{Exporter, Inspection};
+
+/// Denom of `ARCH` in the token map.
+const ARCH_DENOM: &str = "aarch";
 
 pub struct LiquidFinance {
     ctx: Arc<Context>,
     csv: csv::Writer<AddressBalance>,
-    liquid: LiquidFinanceCw20,
+    liquid: Cw20Token,
+    staking: LiquidFinanceStaking,
+    /// `ARCH` redeemed per `sARCH` at the export height.
+    exchange_rate: BigDecimal,
+    /// USD price of `ARCH`, zero when it's unknown.
+    arch_usd: f64,
 }
 
 impl LiquidFinance {
     pub async fn create(ctx: Arc<Context>) -> Result<Self> {
-        let csv = ctx.csv_writer("liquid-finance").await?;
-        let liquid = LiquidFinanceCw20::build(ctx.clone()).await?;
+        let coingecko = ctx
+            .coingecko
+            .clone()
+            .ok_or(anyhow!("missing coingecko client"))?;
+        let csv = ctx.exporter_csv_writer("liquid-finance").await?;
+        let address = ctx
+            .liquid_finance_address
+            .clone()
+            .ok_or(anyhow!("missing liquid finance address"))?;
+        let liquid = Cw20Token::build(ctx.clone(), address).await?;
+        let staking = LiquidFinanceStaking::new(ctx.clone())?;
+        let exchange_rate = staking.exchange_rate().await?;
+
+        let arch_usd = ctx
+            .token_map
+            .get(&ARCH_DENOM.to_string())
+            .and_then(|info| info.coingecko_id.as_deref())
+            .and_then(|id| coingecko.price(vec![id]).get(id).map(|price| price.usd))
+            .unwrap_or_default();
+        tracing::info!(%exchange_rate, arch_usd, "sARCH exchange rate");
 
-        Ok(Self { ctx, csv, liquid })
+        Ok(Self {
+            ctx,
+            csv,
+            liquid,
+            staking,
+            exchange_rate,
+            arch_usd,
+        })
     }
-}
 
-#[async_trait]
-impl Exporter for LiquidFinance {
-    #[tracing::instrument(name = "liquid::export", skip_all, fields(address = token.owner))]
-    async fn export(&self, token: &TokenInfo) -> Result<()> {
-        tracing::info!("exporting Liquid Finance's sARCH balance");
+    async fn collect(&self, owner: &Participant) -> Result<AddressBalance> {
+        let (balance_sarch, pending_claims) = futures::try_join!(
+            self.liquid.balance(owner.address.clone()),
+            self.staking.pending_claims(owner.address.clone())
+        )?;
 
-        let balance = self
-            .liquid
-            .balance(token.owner.clone())
-            .await?
-            .with_scale(2);
-        let balance_score = balance.to_f64().ok_or(anyhow!(
+        // claims are already in ARCH, as their sARCH was burned when unbonding
+        let balance_arch_equivalent =
+            (&balance_sarch * &self.exchange_rate + &pending_claims).with_scale(2);
+        let balance_score = balance_arch_equivalent.to_f64().ok_or(anyhow!(
             "Failed to convert balance amount to f64: {}",
-            balance
+            balance_arch_equivalent
         ))?;
-        let ranking = self
-            .ctx
-            .ranking
-            .ecosystem
-            .activities
-            .astrovault
-            .ranking(balance_score);
+        let usd = balance_score * self.arch_usd;
+        tracing::debug!(%balance_sarch, %pending_claims, usd, "Liquid Finance balance");
+
+        let ranking =
+            self.rank(balance_score) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
 
-        let assets = AddressBalance {
-            address: token.owner.clone(),
-            balance,
+        Ok(AddressBalance {
+            address: owner.address.clone(),
+            balance_sarch: balance_sarch.with_scale(2),
+            pending_claims: pending_claims.with_scale(2),
+            balance_arch_equivalent,
+            usd,
             ranking,
-        };
+        })
+    }
+}
+
+#[async_trait]
+impl Exporter for LiquidFinance {
+    fn name(&self) -> &'static str {
+        "liquid-finance"
+    }
 
+    #[tracing::instrument(name = "liquid::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting Liquid Finance's sARCH balance");
+
+        let assets = self.collect(owner).await?;
         self.csv.write(assets).await?;
 
         tracing::info!("Liquid Finance's sARCH balance export finished");
 
         Ok(())
     }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let assets = self.collect(owner).await?;
+        let ecosystem = &self.ctx.ranking.ecosystem;
+        let weight = ecosystem.activity_weight(&ecosystem.activities.liquid_finance);
+
+        Inspection::new(
+            self.name(),
+            assets.balance_arch_equivalent.to_f64().unwrap_or_default(),
+            assets.ranking,
+            weight,
+            &assets,
+        )
+    }
+
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx
+            .ranking
+            .ecosystem
+            .activities
+            .liquid_finance
+            .ranking(score)
+    }
 }
 
+#[derive(Serialize)]
 pub struct AddressBalance {
     address: String,
-    balance: BigDecimal,
+    balance_sarch: BigDecimal,
+    /// `ARCH` of the unbond claims still held by the staking contract.
+    pending_claims: BigDecimal,
+    /// `ARCH` redeemed for the `sARCH` balance, plus the pending claims.
+    balance_arch_equivalent: BigDecimal,
+    usd: f64,
     ranking: f32,
 }
 
 impl csv::Item for AddressBalance {
     fn header() -> csv::Header {
-        vec!["address", "ranking", "balance"]
+        vec![
+            "address",
+            "ranking",
+            "balance_sarch",
+            "pending_claims",
+            "balance_arch_equivalent",
+            "usd",
+        ]
     }
 
     fn rows(self) -> Vec<csv::Row> {
         vec![vec![
             self.address.clone(),
             format!("{:.2}", self.ranking),
-            self.balance.to_string(),
+            self.balance_sarch.to_string(),
+            self.pending_claims.to_string(),
+            self.balance_arch_equivalent.to_string(),
+            format!("{:.2}", self.usd),
         ]]
     }
 }
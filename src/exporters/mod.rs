@@ -1,44 +1,448 @@
 use async_trait::async_trait;
 use futures::prelude::*;
+use serde::Serialize;
 
-use crate::{prelude::*, queriers::soulbound::TokenInfo};
+use crate::block::BlockId;
+use crate::checkpoint::Checkpoint;
+use crate::config::Activity;
+use crate::csv;
+use crate::manifest::Manifest;
+use crate::metadata::Metadata;
+use crate::participant::{self, Participant};
+use crate::prelude::*;
+
+pub use failures::ErrorPolicy;
+use failures::{FailedExport, Failure};
+pub use history::Aggregation;
+
+/// Maximum number of attempts per address in the `retry-later` mode.
+const MAX_ATTEMPTS: u32 = 3;
 
 mod archid;
 mod astrovault;
+mod cw20;
+mod cw721;
+mod failures;
+mod governance;
+mod history;
 mod ibc;
+mod ibc_transfers;
 mod liquid;
+mod rewards;
+mod smart_query;
 mod socials;
 mod staking;
+mod transactions;
+
+/// Exporters that can be selected from the command line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum ExporterKind {
+    Socials,
+    Ibc,
+    Staking,
+    Archid,
+    LiquidFinance,
+    Astrovault,
+    /// Defined in the `--exporters` config file.
+    Cw20,
+    /// Defined in the `--exporters` config file.
+    Cw721,
+    /// Defined in the `--exporters` config file.
+    SmartQuery,
+    /// Only run when selected with `--only`.
+    Transactions,
+    /// Only run when selected with `--only`.
+    IbcTransfers,
+    /// Only run when selected with `--only`.
+    Governance,
+    /// Only run when selected with `--only`.
+    Rewards,
+}
+
+impl ExporterKind {
+    /// Selects the exporters to run, keeping their default order.
+    /// All of them but the optional ones are selected unless `only` is given.
+    pub fn select(only: &[Self], skip: &[Self]) -> Vec<Self> {
+        <Self as clap::ValueEnum>::value_variants()
+            .iter()
+            .filter(|kind| {
+                if only.is_empty() {
+                    !kind.is_optional()
+                } else {
+                    only.contains(kind)
+                }
+            })
+            .filter(|kind| !skip.contains(kind))
+            .copied()
+            .collect()
+    }
+
+    /// Name of the exporter, which is also the name of its CSV file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Socials => "socials",
+            Self::Ibc => "ibc",
+            Self::Staking => "staking",
+            Self::Archid => "archid",
+            Self::LiquidFinance => "liquid-finance",
+            Self::Astrovault => "astrovault",
+            Self::Rewards => "rewards",
+            Self::Cw20 => "cw20",
+            Self::Cw721 => "cw721",
+            Self::SmartQuery => "smart-query",
+            Self::Transactions => "transactions",
+            Self::IbcTransfers => "ibc-transfers",
+            Self::Governance => "governance",
+        }
+    }
+
+    /// Columns of the CSV file written by the exporter.
+    pub fn header(&self) -> csv::Header {
+        match self {
+            Self::Socials => <socials::AddressSocialPatch as csv::Item>::header(),
+            Self::Ibc => <ibc::AddressBalances as csv::Item>::header(),
+            Self::Staking => <staking::ActiveDelegations as csv::Item>::header(),
+            Self::Archid => <archid::AddressNames as csv::Item>::header(),
+            Self::LiquidFinance => <liquid::AddressBalance as csv::Item>::header(),
+            Self::Astrovault => <astrovault::AstrovaultPosition as csv::Item>::header(),
+            Self::Rewards => <rewards::AddressRewards as csv::Item>::header(),
+            Self::Cw20 => <cw20::AddressTokens as csv::Item>::header(),
+            Self::Cw721 => <cw721::AddressCollections as csv::Item>::header(),
+            Self::SmartQuery => <smart_query::AddressQueries as csv::Item>::header(),
+            Self::Transactions => <transactions::AddressTransactions as csv::Item>::header(),
+            Self::IbcTransfers => <ibc_transfers::AddressTransfers as csv::Item>::header(),
+            Self::Governance => <governance::AddressVotes as csv::Item>::header(),
+        }
+    }
+
+    /// Files written by the exporter besides `<name>.csv`, with several rows per address.
+    pub fn entry_files(&self) -> Vec<EntryFile> {
+        match self {
+            Self::Astrovault => vec![EntryFile::new::<astrovault::AddressPoolPositions>(
+                "astrovault-positions",
+                vec!["pool"],
+            )],
+            Self::Rewards => vec![EntryFile::new::<rewards::AddressContracts>(
+                "rewards-contracts",
+                vec!["contract"],
+            )],
+            Self::Cw20 => vec![EntryFile::new::<cw20::AddressBalances>(
+                "cw20-balances",
+                vec!["contract"],
+            )],
+            Self::Cw721 => vec![EntryFile::new::<cw721::AddressHoldings>(
+                "cw721-holdings",
+                vec!["contract"],
+            )],
+            Self::SmartQuery => vec![EntryFile::new::<smart_query::AddressScores>(
+                "smart-query-scores",
+                vec!["query"],
+            )],
+            Self::IbcTransfers => vec![EntryFile::new::<ibc_transfers::AddressVolumes>(
+                "ibc-transfers-volumes",
+                vec!["direction", "chain", "denom"],
+            )],
+            _ => vec![],
+        }
+    }
+
+    /// Whether the exporter searches the transaction history, which needs an RPC node
+    /// indexing transactions and is slower than querying the state.
+    pub fn is_optional(&self) -> bool {
+        matches!(
+            self,
+            Self::Transactions | Self::IbcTransfers | Self::Governance | Self::Rewards
+        )
+    }
+
+    /// Whether the exporter is defined in the `--exporters` config file.
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Self::Cw20 | Self::Cw721 | Self::SmartQuery)
+    }
+
+    /// Whether the exporter values balances using CoinGecko prices.
+    pub fn needs_prices(&self) -> bool {
+        matches!(
+            self,
+            Self::Ibc | Self::LiquidFinance | Self::IbcTransfers | Self::Rewards
+        )
+    }
+
+    async fn create(self, ctx: Arc<Context>) -> Result<Box<dyn Exporter>> {
+        let exporter: Box<dyn Exporter> = match self {
+            Self::Socials => Box::new(socials::Socials::create(ctx).await?),
+            Self::Ibc => Box::new(ibc::Ibc::create(ctx).await?),
+            Self::Staking => Box::new(staking::Staking::create(ctx).await?),
+            Self::Archid => Box::new(archid::ArchId::create(ctx).await?),
+            Self::LiquidFinance => Box::new(liquid::LiquidFinance::create(ctx).await?),
+            Self::Astrovault => Box::new(astrovault::Astrovault::create(ctx).await?),
+            Self::Rewards => Box::new(rewards::Rewards::create(ctx).await?),
+            Self::Cw20 => Box::new(cw20::Cw20::create(ctx).await?),
+            Self::Cw721 => Box::new(cw721::Cw721::create(ctx).await?),
+            Self::SmartQuery => Box::new(smart_query::SmartQuery::create(ctx).await?),
+            Self::Transactions => Box::new(transactions::Transactions::create(ctx).await?),
+            Self::IbcTransfers => Box::new(ibc_transfers::IbcTransfers::create(ctx).await?),
+            Self::Governance => Box::new(governance::Governance::create(ctx).await?),
+        };
+
+        Ok(exporter)
+    }
+}
+
+/// A file of an exporter with a row per entry of an address, such as a token or a pool.
+pub struct EntryFile {
+    pub name: &'static str,
+    pub header: csv::Header,
+    /// Columns identifying the entry of a row, after the address.
+    pub entry: Vec<&'static str>,
+}
+
+impl EntryFile {
+    fn new<T: csv::Item>(name: &'static str, entry: Vec<&'static str>) -> Self {
+        Self {
+            name,
+            header: T::header(),
+            entry,
+        }
+    }
+}
 
 #[async_trait]
 pub trait Exporter: Sync + Send {
-    async fn export(&self, token: &TokenInfo) -> Result<()>;
+    /// Unique name of the exporter, used to track its progress.
+    fn name(&self) -> &'static str;
+
+    /// Runs once with all the participants before the export starts,
+    /// allowing exporters to prefetch data in bulk.
+    async fn prepare(&self, _owners: &[Participant]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn export(&self, owner: &Participant) -> Result<()>;
+
+    /// Collects the same data as the export without writing it.
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection>;
+
+    /// Ranks a raw activity score, such as one aggregated over several heights.
+    fn rank(&self, score: f64) -> f32;
+}
+
+/// Data collected by an exporter for a single address.
+#[derive(Debug, Serialize)]
+pub struct Inspection {
+    pub exporter: &'static str,
+    /// Raw activity value the ranking is computed from.
+    pub score: f64,
+    pub ranking: f32,
+    /// Weight of the activity in the total ranking.
+    pub weight: f32,
+    pub data: serde_json::Value,
+}
+
+impl Inspection {
+    pub fn new<T>(
+        exporter: &'static str,
+        score: f64,
+        ranking: f32,
+        weight: f32,
+        data: &T,
+    ) -> Result<Self>
+    where
+        T: Serialize,
+    {
+        Ok(Self {
+            exporter,
+            score,
+            ranking,
+            weight,
+            data: serde_json::to_value(data)?,
+        })
+    }
+
+    pub fn weighted_ranking(&self) -> f32 {
+        self.ranking * self.weight
+    }
+}
+
+/// Activity of an item of an exporter defined in the `--exporters` config file,
+/// such as a CW20 token, a CW721 collection or a smart query.
+///
+/// Each item is ranked on the curve of its own `ecosystem` activity, and the score
+/// of the exporter is the [`average_ranking`] of its items, so it's already a ranking.
+fn item_activity<'a>(ctx: &'a Context, activity: &str, item: &str) -> Result<&'a Activity> {
+    ctx.ranking
+        .ecosystem
+        .activities
+        .activity(activity)
+        .ok_or(anyhow!(
+            "missing activity {} of the {} in the ranking config",
+            activity,
+            item
+        ))
+}
+
+/// Average of the rankings weighted by their activity, combining the items
+/// of an exporter defined in the `--exporters` config file.
+fn average_ranking<I>(rankings: I) -> f32
+where
+    I: IntoIterator<Item = (f32, f32)>,
+{
+    let (total, weight) =
+        rankings
+            .into_iter()
+            .fold((0.0, 0.0), |(total, weight), (ranking, activity_weight)| {
+                (total + ranking * activity_weight, weight + activity_weight)
+            });
+
+    if weight == 0.0 {
+        0.0
+    } else {
+        total / weight
+    }
+}
+
+/// Runs the selected exporters for a single address without writing any file.
+pub async fn inspect(ctx: Arc<Context>, owner: &Participant) -> Result<Vec<Inspection>> {
+    let mut inspections = Vec::new();
+
+    for kind in ctx.exporters.iter() {
+        let exporter = kind.create(ctx.clone()).await?;
+        exporter.prepare(std::slice::from_ref(owner)).await?;
+        inspections.push(exporter.inspect(owner).await?);
+    }
+
+    Ok(inspections)
+}
+
+/// Runs the selected exporters at each of the blocks, aggregating their scores.
+pub async fn run_history(
+    ctx: Arc<Context>,
+    blocks: &[BlockId],
+    aggregation: Aggregation,
+) -> Result<()> {
+    history::run(ctx, blocks, aggregation).await
 }
 
 pub async fn run(ctx: Arc<Context>) -> Result<()> {
     tracing::info!("starting data export");
+    let metadata = Metadata::new(&ctx);
 
     ctx.create_output_folder()?;
 
-    let socials_exporter = socials::Socials::create(ctx.clone()).await?;
-    let tokens = socials_exporter.all_tokens().await?;
+    let checkpoint = if ctx.resume {
+        Checkpoint::resume(ctx.output()?).await?
+    } else {
+        let owners = participant::all(ctx.clone()).await?;
+        tracing::info!(count = owners.len(), "total participants");
 
-    let exporters: Vec<Box<dyn Exporter>> = vec![
-        Box::new(socials_exporter),
-        Box::new(ibc::Ibc::create(ctx.clone()).await?),
-        Box::new(staking::Staking::create(ctx.clone()).await?),
-        Box::new(archid::ArchId::create(ctx.clone()).await?),
-        Box::new(liquid::LiquidFinance::create(ctx.clone()).await?),
-        Box::new(astrovault::Astrovault::create(ctx.clone()).await?),
-    ];
+        let height = ctx.cosmos.block.height.value();
+        Checkpoint::create(ctx.output()?, height, owners).await?
+    };
+    let owners = checkpoint.owners();
 
-    stream::iter(tokens.iter())
-        .flat_map(|token| stream::iter(exporters.iter()).map(|exporter| exporter.export(token)))
-        .buffer_unordered(32)
-        .try_collect::<Vec<_>>()
+    metadata.write(&ctx).await?;
+
+    let mut exporters: Vec<Box<dyn Exporter>> = Vec::new();
+    for kind in ctx.exporters.iter() {
+        tracing::info!(exporter = kind.name(), "creating exporter");
+        exporters.push(kind.create(ctx.clone()).await?);
+    }
+
+    for exporter in exporters.iter() {
+        exporter.prepare(owners).await?;
+    }
+
+    let pending: Vec<(&dyn Exporter, &Participant)> = owners
+        .iter()
+        .flat_map(|owner| {
+            exporters
+                .iter()
+                .map(move |exporter| (exporter.as_ref(), owner))
+        })
+        .filter(|(exporter, owner)| !checkpoint.is_completed(exporter.name(), &owner.address))
+        .collect();
+    let total = pending.len();
+
+    let mut failures = export_all(&ctx, &checkpoint, pending, 1).await?;
+    if ctx.on_error == ErrorPolicy::RetryLater {
+        for attempt in 2..=MAX_ATTEMPTS {
+            if failures.is_empty() {
+                break;
+            }
+
+            tracing::info!(attempt, count = failures.len(), "retrying failed exports");
+            let retries = failures
+                .into_iter()
+                .map(|failure| (failure.exporter, failure.owner))
+                .collect();
+            failures = export_all(&ctx, &checkpoint, retries, attempt).await?;
+        }
+    }
+
+    // aborting never leaves failures to report
+    let rate = if ctx.on_error == ErrorPolicy::Abort {
+        drop(failures);
+        0.0
+    } else {
+        let failures = failures.into_iter().map(FailedExport::from).collect();
+        failures::report(&ctx, failures, total).await?
+    };
+
+    // the CSV files are only complete once the exporters release their writers
+    drop(exporters);
+    ctx.flush_csv_writers().await?;
+    Manifest::new(&ctx, metadata, |kind| kind.name().to_string())?
+        .write(ctx.output()?)
         .await?;
 
+    // the files are complete even when too many exports failed
+    failures::check_rate(&ctx, rate)?;
+
     tracing::info!("data export finished");
 
     Ok(())
 }
+
+async fn export_all<'a>(
+    ctx: &Context,
+    checkpoint: &Checkpoint,
+    tasks: Vec<(&'a dyn Exporter, &'a Participant)>,
+    attempt: u32,
+) -> Result<Vec<Failure<'a>>> {
+    stream::iter(tasks)
+        .map(|(exporter, owner)| async move {
+            let export = async {
+                // the rows are flushed once the export returns, so the pair is
+                // never marked complete with its rows still in flight
+                exporter.export(owner).await?;
+                checkpoint.complete(exporter.name(), &owner.address).await
+            };
+
+            let Err(error) = export.await else {
+                return Ok(None);
+            };
+
+            if ctx.on_error == ErrorPolicy::Abort {
+                return Err(error);
+            }
+
+            tracing::warn!(
+                exporter = exporter.name(),
+                address = owner.address,
+                attempt,
+                "export failed: {:#}",
+                error
+            );
+
+            Ok(Some(Failure {
+                exporter,
+                owner,
+                error,
+                attempts: attempt,
+            }))
+        })
+        .buffer_unordered(32)
+        .try_filter_map(|failure| future::ready(Ok(failure)))
+        .try_collect()
+        .await
+}
@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cosmos_sdk_proto::cosmos::base::v1beta1::Coin as ProtoCoin;
+use futures::prelude::*;
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+use tendermint_rpc::endpoint::tx;
+
+use crate::clients::CoinGeckoClient;
+use crate::coin::Coin;
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::{csv, Context};
+
+use super::{Exporter, Inspection};
+
+pub struct Rewards {
+    ctx: Arc<Context>,
+    coingecko: Arc<CoinGeckoClient>,
+    csv: csv::Writer<AddressRewards>,
+    contracts_csv: csv::Writer<AddressContracts>,
+}
+
+impl Rewards {
+    pub async fn create(ctx: Arc<Context>) -> Result<Self> {
+        let coingecko = ctx
+            .coingecko
+            .clone()
+            .ok_or(anyhow!("missing coingecko client"))?;
+        let csv = ctx.exporter_csv_writer("rewards").await?;
+        let contracts_csv = ctx.exporter_csv_writer("rewards-contracts").await?;
+
+        Ok(Self {
+            ctx,
+            coingecko,
+            csv,
+            contracts_csv,
+        })
+    }
+
+    /// Contracts instantiated by the address, or whose rewards metadata
+    /// names it as the owner or the rewards address.
+    async fn contracts(&self, address: &str) -> Result<Vec<Contract>> {
+        let cosmos = &self.ctx.cosmos;
+
+        let created = cosmos
+            .cosmwasm
+            .contracts_by_creator(address.to_string())
+            .await?;
+        let mut named: Vec<String> = cosmos
+            .tx
+            .contract_metadata_sets(address)
+            .map_ok(|tx| metadata_contracts(&tx))
+            .try_concat()
+            .await?;
+        named.sort();
+        named.dedup();
+        named.retain(|contract| !created.contains(contract));
+
+        // contracts with whether they were instantiated by the address
+        let candidates = created
+            .into_iter()
+            .map(|contract| (contract, true))
+            .chain(named.into_iter().map(|contract| (contract, false)));
+
+        let mut contracts = Vec::new();
+        for (contract, created) in candidates {
+            let metadata = cosmos.rewards.contract_metadata(contract.clone()).await?;
+
+            // the metadata may have been changed since it named the address
+            let named = metadata.as_ref().is_some_and(|metadata| {
+                metadata.owner_address == address || metadata.rewards_address == address
+            });
+            if !created && !named {
+                continue;
+            }
+
+            contracts.push(Contract {
+                owner: metadata.as_ref().map(|m| m.owner_address.clone()),
+                rewards_address: metadata.map(|m| m.rewards_address),
+                contract,
+            });
+        }
+
+        Ok(contracts)
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<(AddressRewards, AddressContracts)> {
+        let cosmos = &self.ctx.cosmos;
+        let address = owner.address.clone();
+
+        let (contracts, outstanding, records, withdrawals) = futures::try_join!(
+            self.contracts(&address),
+            cosmos.rewards.outstanding_rewards(address.clone()),
+            cosmos.rewards.rewards_records(address.clone()),
+            cosmos
+                .tx
+                .rewards_withdrawals(&address)
+                .map_ok(|tx| withdrawn_rewards(&tx))
+                .try_collect::<Vec<_>>()
+        )?;
+
+        // the rewards earned are the ones withdrawn so far and the outstanding ones
+        let mut amounts: BTreeMap<String, BigUint> = BTreeMap::new();
+        for coin in outstanding
+            .total_rewards
+            .iter()
+            .chain(withdrawals.iter().flatten())
+        {
+            *amounts.entry(coin.denom.clone()).or_default() += BigUint::from_str(&coin.amount)?;
+        }
+        let withdrawn = withdrawals.len();
+
+        let coins: Vec<Coin> = amounts
+            .into_iter()
+            .map(|(denom, amount)| ProtoCoin {
+                denom,
+                amount: amount.to_string(),
+            })
+            .flat_map(|coin| self.ctx.token_map.create_coin(coin))
+            .flatten()
+            .collect();
+        let ids: Vec<&str> = coins
+            .iter()
+            .flat_map(|coin| coin.coingecko_id.as_deref())
+            .collect();
+        let prices = self.coingecko.price(ids);
+        let usd = coins
+            .iter()
+            .filter_map(|coin| {
+                let price = prices.get(coin.coingecko_id.as_deref()?)?;
+                coin.total_value(price.usd).ok()
+            })
+            .sum();
+
+        let last_record_time = records
+            .iter()
+            .filter_map(|record| record.calculated_time.as_ref())
+            .filter_map(|time| DateTime::from_timestamp(time.seconds, time.nanos as u32))
+            .max();
+        tracing::debug!(
+            contracts = contracts.len(),
+            records = outstanding.records_num,
+            withdrawn,
+            usd,
+            "contract rewards"
+        );
+
+        let ranking = self.rank(usd) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        let rewards = AddressRewards {
+            address: address.clone(),
+            contracts: contracts.len(),
+            rewards_contracts: contracts
+                .iter()
+                .filter(|contract| contract.rewards_address.as_ref() == Some(&address))
+                .count(),
+            records: outstanding.records_num,
+            last_record_time,
+            withdrawn,
+            rewards: coins,
+            usd,
+            ranking,
+        };
+        let contracts = AddressContracts { address, contracts };
+
+        Ok((rewards, contracts))
+    }
+}
+
+#[async_trait]
+impl Exporter for Rewards {
+    fn name(&self) -> &'static str {
+        "rewards"
+    }
+
+    #[tracing::instrument(name = "rewards::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting contract rewards");
+
+        let (rewards, contracts) = self.collect(owner).await?;
+        self.csv.write(rewards).await?;
+        self.contracts_csv.write(contracts).await?;
+
+        tracing::info!("contract rewards export finished");
+
+        Ok(())
+    }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let (rewards, contracts) = self.collect(owner).await?;
+        let archway = &self.ctx.ranking.archway;
+        let weight = archway
+            .activities
+            .rewards
+            .as_ref()
+            .map(|activity| archway.activity_weight(activity))
+            .unwrap_or_default();
+
+        let data = serde_json::json!({
+            "rewards": rewards,
+            "contracts": contracts.contracts,
+        });
+        Inspection::new(self.name(), rewards.usd, rewards.ranking, weight, &data)
+    }
+
+    /// Addresses are not ranked on their rewards unless the activity is configured.
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx
+            .ranking
+            .archway
+            .activities
+            .rewards
+            .as_ref()
+            .map(|activity| activity.ranking(score))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Serialize)]
+pub struct AddressRewards {
+    address: String,
+    /// Contracts instantiated by the address or whose rewards metadata names it.
+    contracts: usize,
+    /// Contracts that pay their rewards to the address.
+    rewards_contracts: usize,
+    /// Rewards records not yet withdrawn.
+    records: u64,
+    last_record_time: Option<DateTime<Utc>>,
+    /// Transactions withdrawing rewards.
+    withdrawn: usize,
+    /// Rewards withdrawn and outstanding.
+    rewards: Vec<Coin>,
+    usd: f64,
+    ranking: f32,
+}
+
+impl csv::Item for AddressRewards {
+    fn header() -> csv::Header {
+        vec![
+            "address",
+            "ranking",
+            "usd",
+            "rewards",
+            "records",
+            "last_record_time",
+            "withdrawn",
+            "contracts",
+            "rewards_contracts",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        let rewards: Vec<String> = self
+            .rewards
+            .iter()
+            .map(|coin| coin.with_scale(2).to_string())
+            .collect();
+
+        vec![vec![
+            self.address,
+            format!("{:.2}", self.ranking),
+            format!("{:.2}", self.usd),
+            rewards.join(","),
+            self.records.to_string(),
+            self.last_record_time
+                .map(|time| time.to_rfc3339())
+                .unwrap_or_default(),
+            self.withdrawn.to_string(),
+            self.contracts.to_string(),
+            self.rewards_contracts.to_string(),
+        ]]
+    }
+}
+
+/// Value of a typed event attribute, which quotes strings as JSON.
+fn attribute_value(value: &str) -> String {
+    serde_json::from_str(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Contracts whose rewards metadata is set in a transaction.
+fn metadata_contracts(tx: &tx::Response) -> Vec<String> {
+    tx.tx_result
+        .events
+        .iter()
+        .filter(|event| event.kind == "archway.rewards.v1.ContractMetadataSetEvent")
+        .flat_map(|event| event.attributes.iter())
+        .filter(|attr| attr.key == "contract_address")
+        .map(|attr| attribute_value(&attr.value))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct EventCoin {
+    denom: String,
+    amount: String,
+}
+
+/// Rewards withdrawn in a transaction.
+fn withdrawn_rewards(tx: &tx::Response) -> Vec<ProtoCoin> {
+    tx.tx_result
+        .events
+        .iter()
+        .filter(|event| event.kind == "archway.rewards.v1.RewardsWithdrawEvent")
+        .flat_map(|event| event.attributes.iter())
+        .filter(|attr| attr.key == "rewards")
+        .flat_map(|attr| serde_json::from_str::<Vec<EventCoin>>(&attr.value).unwrap_or_default())
+        .map(|coin| ProtoCoin {
+            denom: coin.denom,
+            amount: coin.amount,
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct Contract {
+    contract: String,
+    /// Owner of the rewards metadata, `None` when it was never set.
+    owner: Option<String>,
+    rewards_address: Option<String>,
+}
+
+pub struct AddressContracts {
+    address: String,
+    contracts: Vec<Contract>,
+}
+
+impl csv::Item for AddressContracts {
+    fn header() -> csv::Header {
+        vec!["address", "contract", "owner", "rewards_address"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        self.contracts
+            .into_iter()
+            .map(|contract| {
+                vec![
+                    self.address.clone(),
+                    contract.contract,
+                    contract.owner.unwrap_or_default(),
+                    contract.rewards_address.unwrap_or_default(),
+                ]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,212 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use bigdecimal::{BigDecimal, Zero};
+use serde::Serialize;
+
+use crate::config::SmartQueryExporter;
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::{csv, Context};
+
+use super::{average_ranking, item_activity, Exporter, Inspection};
+
+/// Placeholder of the query templates replaced by the participant address.
+const ADDRESS_PLACEHOLDER: &str = "{address}";
+
+pub struct SmartQuery {
+    ctx: Arc<Context>,
+    csv: csv::Writer<AddressQueries>,
+    scores_csv: csv::Writer<AddressScores>,
+}
+
+impl SmartQuery {
+    pub async fn create(ctx: Arc<Context>) -> Result<Self> {
+        for query in ctx.custom_exporters.smart_query.iter() {
+            item_activity(&ctx, &query.activity, &format!("{} query", query.name))?;
+
+            // checks the template is valid JSON before querying any address
+            render(query, "archway1").map_err(|err| {
+                anyhow!(
+                    "invalid query template of the {} query: {}",
+                    query.name,
+                    err
+                )
+            })?;
+        }
+        tracing::info!(
+            count = ctx.custom_exporters.smart_query.len(),
+            "smart queries"
+        );
+
+        let csv = ctx.exporter_csv_writer("smart-query").await?;
+        let scores_csv = ctx.exporter_csv_writer("smart-query-scores").await?;
+
+        Ok(Self {
+            ctx,
+            csv,
+            scores_csv,
+        })
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<(AddressQueries, AddressScores)> {
+        let mut scores = Vec::new();
+        for query in self.ctx.custom_exporters.smart_query.iter() {
+            let score = self.score(query, &owner.address).await?.with_scale(2);
+            let activity =
+                item_activity(&self.ctx, &query.activity, &format!("{} query", query.name))?;
+
+            scores.push(Score {
+                query: query.name.clone(),
+                contract: query.contract.clone(),
+                weight: activity.weight,
+                ranking: activity.ranking(score.to_f64().unwrap_or_default()),
+                score,
+            });
+        }
+
+        let score = average_ranking(scores.iter().map(|score| (score.ranking, score.weight)));
+        let ranking =
+            self.rank(score as f64) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        let queries = AddressQueries {
+            address: owner.address.clone(),
+            queries: scores.iter().filter(|score| !score.score.is_zero()).count(),
+            score: score as f64,
+            ranking,
+        };
+        let scores = AddressScores {
+            address: owner.address.clone(),
+            scores,
+        };
+
+        Ok((queries, scores))
+    }
+
+    /// Number selected in the query response, zero when it's `null`.
+    #[tracing::instrument(skip(self, query), fields(query = query.name))]
+    async fn score(&self, query: &SmartQueryExporter, address: &str) -> Result<BigDecimal> {
+        let msg = render(query, address)?;
+        let response: serde_json::Value = self
+            .ctx
+            .query_contract(query.contract.clone(), &msg)
+            .await?;
+        tracing::debug!(%response, "smart query response");
+
+        let value = query.selector.select(&response).ok_or(anyhow!(
+            "{} not found in the query response",
+            query.selector
+        ))?;
+        let amount = match value {
+            serde_json::Value::Null => BigDecimal::default(),
+            serde_json::Value::Number(number) => BigDecimal::from_str(&number.to_string())?,
+            serde_json::Value::String(number) => BigDecimal::from_str(number)?,
+            value => return Err(anyhow!("{} is not a number: {}", query.selector, value)),
+        };
+
+        Ok(amount * BigDecimal::new(1.into(), query.decimals.into()))
+    }
+}
+
+/// Query message of the template for an address.
+fn render(query: &SmartQueryExporter, address: &str) -> Result<serde_json::Value> {
+    let msg = query.query.replace(ADDRESS_PLACEHOLDER, address);
+    Ok(serde_json::from_str(&msg)?)
+}
+
+#[async_trait]
+impl Exporter for SmartQuery {
+    fn name(&self) -> &'static str {
+        "smart-query"
+    }
+
+    #[tracing::instrument(name = "smart_query::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting smart queries");
+
+        let (queries, scores) = self.collect(owner).await?;
+        self.csv.write(queries).await?;
+        self.scores_csv.write(scores).await?;
+
+        tracing::info!("smart queries export finished");
+
+        Ok(())
+    }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let (queries, scores) = self.collect(owner).await?;
+        let weight = self.ctx.ranking.ecosystem.weight
+            * scores.scores.iter().map(|score| score.weight).sum::<f32>();
+
+        let data = serde_json::json!({
+            "queries": queries,
+            "scores": scores.scores,
+        });
+        Inspection::new(self.name(), queries.score, queries.ranking, weight, &data)
+    }
+
+    /// The score is already a ranking, see [`item_activity`].
+    fn rank(&self, score: f64) -> f32 {
+        score as f32
+    }
+}
+
+#[derive(Serialize)]
+pub struct AddressQueries {
+    address: String,
+    /// Configured queries with a score for the address.
+    queries: usize,
+    #[serde(skip)]
+    score: f64,
+    ranking: f32,
+}
+
+impl csv::Item for AddressQueries {
+    fn header() -> csv::Header {
+        vec!["address", "ranking", "queries"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.address,
+            format!("{:.2}", self.ranking),
+            self.queries.to_string(),
+        ]]
+    }
+}
+
+#[derive(Serialize)]
+struct Score {
+    query: String,
+    contract: String,
+    score: BigDecimal,
+    /// Weight of the query activity in the `ecosystem` group.
+    weight: f32,
+    ranking: f32,
+}
+
+pub struct AddressScores {
+    address: String,
+    scores: Vec<Score>,
+}
+
+impl csv::Item for AddressScores {
+    fn header() -> csv::Header {
+        vec!["address", "query", "contract", "score", "ranking"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        self.scores
+            .into_iter()
+            .map(|score| {
+                vec![
+                    self.address.clone(),
+                    score.query,
+                    score.contract,
+                    score.score.to_string(),
+                    format!("{:.2}", score.ranking),
+                ]
+            })
+            .collect()
+    }
+}
@@ -1,75 +1,94 @@
 use async_trait::async_trait;
+use serde::Serialize;
 
+use crate::participant::Participant;
 use crate::prelude::*;
-use crate::queriers::soulbound::{SoulboundToken, TokenInfo};
 use crate::{csv, Context};
 
-use super::Exporter;
+use super::{Exporter, Inspection};
 
 pub struct Socials {
     ctx: Arc<Context>,
     csv: csv::Writer<AddressSocialPatch>,
-    soulbound_token: SoulboundToken,
 }
 
 impl Socials {
     pub async fn create(ctx: Arc<Context>) -> Result<Self> {
-        let csv = ctx.csv_writer("socials").await?;
-        let soulbound_token = SoulboundToken::new(ctx.clone());
-
-        Ok(Self {
-            csv,
-            ctx,
-            soulbound_token,
-        })
+        let csv = ctx.exporter_csv_writer("socials").await?;
+        Ok(Self { csv, ctx })
     }
 
-    pub async fn all_tokens(&self) -> Result<Vec<TokenInfo>> {
-        let tokens = self.soulbound_token.all_tokens().await?;
-        Ok(tokens)
+    async fn collect(&self, owner: &Participant) -> Result<AddressSocialPatch> {
+        let ranking = self.rank(owner.social_score as f64)
+            * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        Ok(AddressSocialPatch {
+            address: owner.address.clone(),
+            patch_names: owner.patch_names.clone(),
+            social_score: owner.social_score,
+            ranking,
+        })
     }
 }
 
 #[async_trait]
 impl Exporter for Socials {
-    #[tracing::instrument(name = "social::export", skip_all, fields(address = token.owner))]
-    async fn export(&self, token: &TokenInfo) -> Result<()> {
-        tracing::info!("exporting soulbound patches");
-
-        let ranking = self.ctx.ranking.social.weighted_ranking(token.social_score);
+    fn name(&self) -> &'static str {
+        "socials"
+    }
 
-        let assets = AddressSocialPatch {
-            address: token.owner.clone(),
-            patch_name: token.name.clone(),
-            social_score: token.social_score,
-            ranking,
-        };
+    #[tracing::instrument(name = "social::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting soulbound patches");
 
+        let assets = self.collect(owner).await?;
         self.csv.write(assets).await?;
 
         tracing::info!("soulbound patches export finished");
 
         Ok(())
     }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let assets = self.collect(owner).await?;
+        // the social ranking is already weighted by the group weight
+        let weight = 1.0;
+
+        Inspection::new(
+            self.name(),
+            assets.social_score as f64,
+            assets.ranking,
+            weight,
+            &assets,
+        )
+    }
+
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx
+            .ranking
+            .social
+            .weighted_ranking(score.round() as u16)
+    }
 }
 
+#[derive(Serialize)]
 pub struct AddressSocialPatch {
     address: String,
-    patch_name: String,
+    patch_names: Vec<String>,
     social_score: u16,
     ranking: f32,
 }
 
 impl csv::Item for AddressSocialPatch {
     fn header() -> csv::Header {
-        vec!["address", "ranking", "patch_name", "social_score"]
+        vec!["address", "ranking", "patch_names", "social_score"]
     }
 
     fn rows(self) -> Vec<csv::Row> {
         vec![vec![
             self.address.clone(),
             format!("{:.2}", self.ranking),
-            self.patch_name.clone(),
+            self.patch_names.join(","),
             self.social_score.to_string(),
         ]]
     }
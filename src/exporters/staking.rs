@@ -2,12 +2,13 @@ use std::collections::HashMap;
 
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
+use serde::Serialize;
 
+use crate::participant::Participant;
 use crate::prelude::*;
-use crate::queriers::soulbound::TokenInfo;
 use crate::{csv, Context};
 
-use super::Exporter;
+use super::{Exporter, Inspection};
 
 pub struct Staking {
     ctx: Arc<Context>,
@@ -16,22 +17,16 @@ pub struct Staking {
 
 impl Staking {
     pub async fn create(ctx: Arc<Context>) -> Result<Self> {
-        let csv = ctx.csv_writer("staking").await?;
+        let csv = ctx.exporter_csv_writer("staking").await?;
         Ok(Self { ctx, csv })
     }
-}
-
-#[async_trait]
-impl Exporter for Staking {
-    #[tracing::instrument(name = "staking::export", skip_all, fields(address = token.owner))]
-    async fn export(&self, token: &TokenInfo) -> Result<()> {
-        tracing::info!("exporting delegations");
 
+    async fn collect(&self, owner: &Participant) -> Result<ActiveDelegations> {
         let response = self
             .ctx
             .cosmos
             .staking
-            .delegations(token.owner.clone())
+            .delegations(owner.address.clone())
             .await?;
 
         let delegations: HashMap<String, BigDecimal> = response
@@ -50,13 +45,10 @@ impl Exporter for Staking {
             })
             .collect();
 
-        let validators: Vec<String> = delegations
-            .iter()
-            .map(|(validator, _amount)| validator.clone())
-            .collect();
+        let validators: Vec<String> = delegations.keys().cloned().collect();
         let delegated: BigDecimal = delegations
-            .iter()
-            .map(|(_validator, amount)| amount.with_scale(2))
+            .values()
+            .map(|amount| amount.with_scale(2))
             .sum();
         tracing::debug!(%delegated, ?validators, "total delegations");
 
@@ -64,29 +56,56 @@ impl Exporter for Staking {
             "Failed to convert delegated amount to f64: {}",
             delegated
         ))?;
-        let ranking = self
-            .ctx
-            .ranking
-            .archway
-            .activities
-            .stake
-            .ranking(delegated_score);
-
-        let active_delegations = ActiveDelegations {
-            address: token.owner.clone(),
+        let ranking =
+            self.rank(delegated_score) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        Ok(ActiveDelegations {
+            address: owner.address.clone(),
             validators,
             delegated,
             ranking,
-        };
+        })
+    }
+}
 
+#[async_trait]
+impl Exporter for Staking {
+    fn name(&self) -> &'static str {
+        "staking"
+    }
+
+    #[tracing::instrument(name = "staking::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting delegations");
+
+        let active_delegations = self.collect(owner).await?;
         self.csv.write(active_delegations).await?;
 
         tracing::info!("delegations export finished");
 
         Ok(())
     }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let active_delegations = self.collect(owner).await?;
+        let archway = &self.ctx.ranking.archway;
+        let weight = archway.activity_weight(&archway.activities.stake);
+
+        Inspection::new(
+            self.name(),
+            active_delegations.delegated.to_f64().unwrap_or_default(),
+            active_delegations.ranking,
+            weight,
+            &active_delegations,
+        )
+    }
+
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx.ranking.archway.activities.stake.ranking(score)
+    }
 }
 
+#[derive(Serialize)]
 pub struct ActiveDelegations {
     address: String,
     validators: Vec<String>,
@@ -0,0 +1,193 @@
+use std::collections::{BTreeSet, HashSet};
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use serde::Serialize;
+use tendermint::Hash;
+use tendermint_rpc::endpoint::tx;
+
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::{csv, Context};
+
+use super::{Exporter, Inspection};
+
+pub struct Transactions {
+    ctx: Arc<Context>,
+    csv: csv::Writer<AddressTransactions>,
+}
+
+impl Transactions {
+    pub async fn create(ctx: Arc<Context>) -> Result<Self> {
+        let csv = ctx.exporter_csv_writer("transactions").await?;
+        Ok(Self { ctx, csv })
+    }
+
+    async fn collect(&self, owner: &Participant) -> Result<AddressTransactions> {
+        let address = owner.address.as_str();
+        let tx = &self.ctx.cosmos.tx;
+
+        // only what's needed of each transaction is kept while paging through them
+        let (sent, received) = futures::try_join!(
+            tx.sent(address, self.ctx.since_height).try_fold(
+                SentTransactions::default(),
+                |mut sent, tx| {
+                    sent.add(&tx);
+                    future::ready(Ok(sent))
+                }
+            ),
+            tx.received(address, self.ctx.since_height)
+                .map_ok(|tx| (tx.hash, tx.height.value()))
+                .try_collect::<Vec<_>>()
+        )?;
+
+        let received: Vec<u64> = received
+            .into_iter()
+            .filter(|(hash, _)| !sent.hashes.contains(hash))
+            .map(|(_, height)| height)
+            .collect();
+
+        let heights = || sent.heights.iter().chain(received.iter()).copied();
+        let txs = sent.hashes.len();
+        let gas_used = sent.gas_used;
+        tracing::debug!(
+            txs,
+            received = received.len(),
+            gas_used,
+            "transactions found"
+        );
+
+        let ranking = self.rank(txs as f64) * self.ctx.ranking.sybil_factor(owner.sybil_confidence);
+
+        Ok(AddressTransactions {
+            address: owner.address.clone(),
+            txs,
+            received: received.len(),
+            first_height: heights().min(),
+            last_height: heights().max(),
+            contracts: sent.contracts.into_iter().collect(),
+            gas_used,
+            ranking,
+        })
+    }
+}
+
+/// Transactions sent by an address, reduced to what the export needs.
+#[derive(Default)]
+struct SentTransactions {
+    hashes: HashSet<Hash>,
+    heights: Vec<u64>,
+    contracts: BTreeSet<String>,
+    gas_used: u64,
+}
+
+impl SentTransactions {
+    fn add(&mut self, tx: &tx::Response) {
+        self.hashes.insert(tx.hash);
+        self.heights.push(tx.height.value());
+        self.contracts.extend(
+            tx.tx_result
+                .events
+                .iter()
+                .filter(|event| event.kind == "execute")
+                .flat_map(|event| event.attributes.iter())
+                .filter(|attr| attr.key == "_contract_address")
+                .map(|attr| attr.value.clone()),
+        );
+        self.gas_used += u64::try_from(tx.tx_result.gas_used).unwrap_or_default();
+    }
+}
+
+#[async_trait]
+impl Exporter for Transactions {
+    fn name(&self) -> &'static str {
+        "transactions"
+    }
+
+    #[tracing::instrument(name = "transactions::export", skip_all, fields(address = owner.address))]
+    async fn export(&self, owner: &Participant) -> Result<()> {
+        tracing::info!("exporting transactions");
+
+        let transactions = self.collect(owner).await?;
+        self.csv.write(transactions).await?;
+
+        tracing::info!("transactions export finished");
+
+        Ok(())
+    }
+
+    async fn inspect(&self, owner: &Participant) -> Result<Inspection> {
+        let transactions = self.collect(owner).await?;
+        let archway = &self.ctx.ranking.archway;
+        let weight = archway
+            .activities
+            .transactions
+            .as_ref()
+            .map(|activity| archway.activity_weight(activity))
+            .unwrap_or_default();
+
+        Inspection::new(
+            self.name(),
+            transactions.txs as f64,
+            transactions.ranking,
+            weight,
+            &transactions,
+        )
+    }
+
+    /// Addresses are not ranked on their transactions unless the activity is configured.
+    fn rank(&self, score: f64) -> f32 {
+        self.ctx
+            .ranking
+            .archway
+            .activities
+            .transactions
+            .as_ref()
+            .map(|activity| activity.ranking(score))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Serialize)]
+pub struct AddressTransactions {
+    address: String,
+    /// Transactions with a message sent by the address.
+    txs: usize,
+    /// Other transactions transferring tokens to the address.
+    received: usize,
+    first_height: Option<u64>,
+    last_height: Option<u64>,
+    contracts: Vec<String>,
+    gas_used: u64,
+    ranking: f32,
+}
+
+impl csv::Item for AddressTransactions {
+    fn header() -> csv::Header {
+        vec![
+            "address",
+            "ranking",
+            "txs",
+            "received",
+            "first_height",
+            "last_height",
+            "contracts",
+            "gas_used",
+        ]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        let height = |height: Option<u64>| height.map(|h| h.to_string()).unwrap_or_default();
+
+        vec![vec![
+            self.address.clone(),
+            format!("{:.2}", self.ranking),
+            self.txs.to_string(),
+            self.received.to_string(),
+            height(self.first_height),
+            height(self.last_height),
+            self.contracts.len().to_string(),
+            self.gas_used.to_string(),
+        ]]
+    }
+}
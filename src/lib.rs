@@ -1,13 +1,20 @@
+mod address;
 mod app;
 mod block;
+mod checkpoint;
 mod clients;
 mod coin;
 mod config;
 mod context;
 mod csv;
+mod diff;
 mod exporters;
+mod manifest;
+mod metadata;
+mod participant;
 pub mod prelude;
 mod queriers;
+mod sybil;
 mod utils;
 
 pub use app::App;
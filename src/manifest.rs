@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::exporters::ExporterKind;
+use crate::metadata::Metadata;
+use crate::prelude::*;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Files of the output directory that change while resuming, so they are not checksummed.
+const EXCLUDED_FILES: [&str; 3] = [MANIFEST_FILE, "checkpoint.json", "checkpoint.log"];
+
+/// Everything that produced an export, written once it finishes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Same details as `metadata.json`, written when the export started.
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    pub finished_at: DateTime<Utc>,
+    pub rpc_url: String,
+    pub contracts: Contracts,
+    pub ranking: ConfigFile,
+    pub token_map: ConfigFile,
+    #[serde(default)]
+    pub custom_exporters: Option<ConfigFile>,
+    /// Number of rows written by each exporter, excluding the header.
+    pub rows: BTreeMap<String, usize>,
+    /// SHA-256 checksum of each output file.
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Contracts {
+    pub soulbound: Option<String>,
+    pub archid: Option<String>,
+    pub liquid_finance: Option<String>,
+    #[serde(default)]
+    pub liquid_finance_staking: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+impl ConfigFile {
+    fn new(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            sha256: checksum(path)?,
+        })
+    }
+}
+
+impl Manifest {
+    /// Builds the manifest from the files in the output directory,
+    /// which must be fully written. The rows of each exporter are counted
+    /// in the CSV file named by `file`.
+    pub fn new(
+        ctx: &Context,
+        metadata: Metadata,
+        file: impl Fn(&ExporterKind) -> String,
+    ) -> Result<Self> {
+        let output = ctx.output()?;
+
+        let files = output_files(output)?
+            .into_iter()
+            .map(|name| {
+                let sha256 = checksum(&output.join(&name))?;
+                Ok((name, sha256))
+            })
+            .collect::<Result<_>>()?;
+
+        let rows = ctx
+            .exporters
+            .iter()
+            .map(|kind| {
+                let path = output.join(file(kind)).with_extension("csv");
+                let lines = std::fs::read_to_string(path)?.lines().count();
+                Ok((kind.name().to_string(), lines.saturating_sub(1)))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            metadata,
+            finished_at: Utc::now(),
+            rpc_url: ctx.rpc_url().to_string(),
+            contracts: Contracts {
+                soulbound: ctx.soulbound_address.clone(),
+                archid: ctx.archid_address.clone(),
+                liquid_finance: ctx.liquid_finance_address.clone(),
+                liquid_finance_staking: ctx.liquid_finance_staking_address.clone(),
+            },
+            ranking: ConfigFile::new(&ctx.ranking_path)?,
+            token_map: ConfigFile::new(&ctx.token_map_path)?,
+            custom_exporters: ctx
+                .custom_exporters_path
+                .as_deref()
+                .map(ConfigFile::new)
+                .transpose()?,
+            rows,
+            files,
+        })
+    }
+
+    pub async fn write(&self, output: &Path) -> Result<()> {
+        let path = output.join(MANIFEST_FILE);
+        tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+        tracing::info!(files = self.files.len(), "manifest written");
+
+        Ok(())
+    }
+
+    pub fn read(output: &Path) -> Result<Self> {
+        let path = output.join(MANIFEST_FILE);
+        let content = std::fs::read(&path)
+            .map_err(|err| anyhow!("failed to read {}: {}", path.display(), err))?;
+
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Checks the output files against the manifest checksums, returning the problems found.
+    pub fn verify(&self, output: &Path) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+
+        for (name, expected) in self.files.iter() {
+            let path = output.join(name);
+            if !path.exists() {
+                problems.push(format!("{}: missing", name));
+                continue;
+            }
+
+            let actual = checksum(&path)?;
+            if actual != *expected {
+                problems.push(format!("{}: checksum mismatch", name));
+            }
+        }
+
+        Ok(problems)
+    }
+}
+
+/// Names of the files in the output directory covered by the manifest, sorted.
+fn output_files(output: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(output)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_file() && !EXCLUDED_FILES.contains(&name.as_str()) {
+            files.push(name);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+fn checksum(path: &Path) -> Result<String> {
+    let content =
+        std::fs::read(path).map_err(|err| anyhow!("failed to read {}: {}", path.display(), err))?;
+
+    Ok(hex::encode(Sha256::digest(content)))
+}
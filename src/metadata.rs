@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::block::BlockId;
+use crate::prelude::*;
+
+const METADATA_FILE: &str = "metadata.json";
+
+/// Details of an export run, written next to its CSV files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    pub version: String,
+    pub created_at: DateTime<Utc>,
+    /// Height of the block the queries ran on.
+    pub height: u64,
+    pub time: DateTime<Utc>,
+    /// Time given with `--at`, when the block was searched by time.
+    pub requested_time: Option<DateTime<Utc>>,
+    pub exporters: Vec<String>,
+}
+
+impl Metadata {
+    pub fn new(ctx: &Context) -> Self {
+        let requested_time = match ctx.requested_block {
+            Some(BlockId::Time(time)) => Some(time),
+            _ => None,
+        };
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            height: ctx.cosmos.block.height.value(),
+            time: ctx.cosmos.block.time,
+            requested_time,
+            exporters: ctx
+                .exporters
+                .iter()
+                .map(|kind| kind.name().to_string())
+                .collect(),
+        }
+    }
+
+    pub async fn write(&self, ctx: &Context) -> Result<()> {
+        let path = ctx.output()?.join(METADATA_FILE);
+        tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::address::Address;
+use crate::csv;
+use crate::prelude::*;
+use crate::queriers::soulbound::{SocialScorePolicy, SoulboundToken, TokenInfo};
+use crate::sybil;
+
+/// An address taking part in the drop camp, whatever the source it comes from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Participant {
+    pub address: String,
+    /// Names of the soulbound tokens held by the address, if any.
+    pub patch_names: Vec<String>,
+    pub social_score: u16,
+    /// Confidence of the address belonging to a sybil cluster, when found in one.
+    #[serde(default)]
+    pub sybil_confidence: Option<f32>,
+}
+
+impl Participant {
+    /// Creates a participant that does not hold any soulbound token.
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            patch_names: vec![],
+            social_score: 0,
+            sybil_confidence: None,
+        }
+    }
+
+    /// Groups the soulbound tokens by owner, keeping the order in which owners first appear.
+    pub fn from_tokens(tokens: Vec<TokenInfo>, policy: SocialScorePolicy) -> Vec<Participant> {
+        let mut owners: Vec<(String, Vec<TokenInfo>)> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+
+        for token in tokens {
+            match index.get(&token.owner) {
+                Some(&i) => owners[i].1.push(token),
+                None => {
+                    index.insert(token.owner.clone(), owners.len());
+                    owners.push((token.owner.clone(), vec![token]));
+                }
+            }
+        }
+
+        owners
+            .into_iter()
+            .map(|(address, mut tokens)| {
+                tokens.sort_by(|a, b| compare_ids(&a.id, &b.id));
+
+                let social_score = match policy {
+                    SocialScorePolicy::Max => tokens.iter().map(|t| t.social_score).max(),
+                    SocialScorePolicy::Sum => tokens
+                        .iter()
+                        .map(|t| t.social_score)
+                        .reduce(u16::saturating_add),
+                    SocialScorePolicy::Latest => tokens.last().map(|t| t.social_score),
+                }
+                .unwrap_or_default();
+
+                if tokens.len() > 1 {
+                    tracing::debug!(
+                        address,
+                        count = tokens.len(),
+                        social_score,
+                        "address owns multiple soulbound tokens"
+                    );
+                }
+
+                Participant {
+                    address,
+                    patch_names: tokens.into_iter().map(|t| t.name).collect(),
+                    social_score,
+                    sybil_confidence: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Token ids are minted sequentially, so numeric ids are compared as numbers.
+fn compare_ids(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (std::result::Result::Ok(a), std::result::Result::Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Collects the participants from the soulbound token holders and the given address list,
+/// looking for sybil clusters among them when enabled.
+pub async fn all(ctx: Arc<Context>) -> Result<Vec<Participant>> {
+    let mut participants = match &ctx.addresses {
+        Some(_) if !ctx.merge_addresses => vec![],
+        _ => {
+            let tokens = SoulboundToken::new(ctx.clone())?.all_tokens().await?;
+            let owners = Participant::from_tokens(tokens, ctx.social_score_policy);
+            tracing::info!(count = owners.len(), "unique soulbound token owners");
+            owners
+        }
+    };
+
+    if let Some(addresses) = &ctx.addresses {
+        let mut known: HashSet<String> = participants.iter().map(|p| p.address.clone()).collect();
+        let before = participants.len();
+
+        participants.extend(
+            addresses
+                .iter()
+                .filter(|address| known.insert(address.to_string()))
+                .map(|address| Participant::new(address.clone())),
+        );
+        tracing::info!(
+            count = participants.len() - before,
+            "participants added from the address list"
+        );
+    }
+
+    let mut participants = validate(&ctx, participants).await?;
+    derive(&ctx, &participants).await?;
+
+    if ctx.sybil {
+        sybil::analyze(ctx.clone(), &mut participants).await?;
+    }
+
+    Ok(participants)
+}
+
+/// Keeps the participants with a valid account address, normalizing its case.
+/// Invalid and contract addresses are written to `invalid-addresses.csv`.
+async fn validate(ctx: &Context, participants: Vec<Participant>) -> Result<Vec<Participant>> {
+    let csv = ctx.csv_writer("invalid-addresses").await?;
+    let total = participants.len();
+    let mut known: HashSet<String> = HashSet::new();
+    let mut valid = Vec::with_capacity(total);
+
+    for mut participant in participants {
+        match Address::parse(&participant.address) {
+            std::result::Result::Ok(address) => {
+                participant.address = address.to_string();
+                if known.insert(participant.address.clone()) {
+                    valid.push(participant);
+                }
+            }
+            Err(reason) => {
+                tracing::warn!(address = participant.address, %reason, "skipping participant");
+                csv.write(InvalidParticipant {
+                    address: participant.address,
+                    reason: reason.to_string(),
+                })
+                .await?;
+            }
+        }
+    }
+
+    tracing::info!(
+        count = valid.len(),
+        skipped = total - valid.len(),
+        "valid participants"
+    );
+
+    Ok(valid)
+}
+
+/// Writes the participant accounts encoded with the prefixes of other Cosmos chains
+/// to `derived-addresses.csv`.
+async fn derive(ctx: &Context, participants: &[Participant]) -> Result<()> {
+    if ctx.derive_prefixes.is_empty() {
+        return Ok(());
+    }
+
+    let csv = ctx.csv_writer("derived-addresses").await?;
+    for participant in participants {
+        let address = Address::parse(&participant.address)?;
+        let derived = ctx
+            .derive_prefixes
+            .iter()
+            .map(|prefix| Ok((prefix.clone(), address.derive(prefix)?)))
+            .collect::<Result<_>>()?;
+
+        csv.write(DerivedAddresses {
+            address: participant.address.clone(),
+            derived,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Collects a single participant, with its soulbound tokens when the contract is known.
+pub async fn one(ctx: Arc<Context>, address: &str) -> Result<Participant> {
+    let address = Address::parse(address)
+        .map_err(|reason| anyhow!("invalid address {}: {}", address, reason))?;
+    let address = address.as_str();
+
+    let participant = match ctx.soulbound_address {
+        Some(_) => {
+            let tokens = SoulboundToken::new(ctx.clone())?.tokens_of(address).await?;
+            Participant::from_tokens(tokens, ctx.social_score_policy).pop()
+        }
+        None => None,
+    };
+
+    Ok(participant.unwrap_or_else(|| Participant::new(address.to_string())))
+}
+
+/// Reads a list of addresses from a file.
+///
+/// Plain text files contain one address per line, while CSV files use the first column,
+/// skipping an optional `address` header. Empty lines and lines starting with `#` are ignored.
+pub fn read_addresses(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| anyhow!("failed to read {}: {}", path.display(), err))?;
+    let is_csv = path.extension().is_some_and(|ext| ext == "csv");
+
+    let addresses: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            if is_csv {
+                line.split([',', ';']).next().map(str::trim)
+            } else {
+                Some(line)
+            }
+        })
+        .filter(|address| !address.eq_ignore_ascii_case("address"))
+        .map(str::to_string)
+        .collect();
+
+    tracing::info!(path = %path.display(), count = addresses.len(), "address list loaded");
+
+    Ok(addresses)
+}
+
+pub struct InvalidParticipant {
+    address: String,
+    reason: String,
+}
+
+impl csv::Item for InvalidParticipant {
+    fn header() -> csv::Header {
+        vec!["address", "reason"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![self.address, self.reason]]
+    }
+}
+
+pub struct DerivedAddresses {
+    address: String,
+    /// Pairs of prefix and derived address.
+    derived: Vec<(String, String)>,
+}
+
+impl csv::Item for DerivedAddresses {
+    fn header() -> csv::Header {
+        vec!["address", "prefix", "derived_address"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        self.derived
+            .into_iter()
+            .map(|(prefix, derived)| vec![self.address.clone(), prefix, derived])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(id: &str, owner: &str, social_score: u16) -> TokenInfo {
+        TokenInfo {
+            id: id.to_string(),
+            name: format!("patch-{}", id),
+            owner: owner.to_string(),
+            social_score,
+        }
+    }
+
+    /// Tokens of two owners, out of minting order for the first one.
+    fn tokens() -> Vec<TokenInfo> {
+        vec![
+            token("10", "archway1first", 30),
+            token("2", "archway1first", 50),
+            token("3", "archway1second", 20),
+            token("9", "archway1first", 10),
+        ]
+    }
+
+    fn scores(policy: SocialScorePolicy) -> Vec<(String, u16)> {
+        Participant::from_tokens(tokens(), policy)
+            .into_iter()
+            .map(|p| (p.address, p.social_score))
+            .collect()
+    }
+
+    #[test]
+    fn groups_tokens_by_owner() {
+        let participants = Participant::from_tokens(tokens(), SocialScorePolicy::Max);
+
+        assert_eq!(participants.len(), 2);
+        assert_eq!(participants[0].address, "archway1first");
+        assert_eq!(
+            participants[0].patch_names,
+            ["patch-2", "patch-9", "patch-10"]
+        );
+        assert_eq!(participants[1].address, "archway1second");
+    }
+
+    #[test]
+    fn max_policy() {
+        assert_eq!(
+            scores(SocialScorePolicy::Max),
+            [("archway1first".into(), 50), ("archway1second".into(), 20)]
+        );
+    }
+
+    #[test]
+    fn sum_policy() {
+        assert_eq!(
+            scores(SocialScorePolicy::Sum),
+            [("archway1first".into(), 90), ("archway1second".into(), 20)]
+        );
+    }
+
+    #[test]
+    fn sum_policy_saturates() {
+        let tokens = vec![
+            token("1", "archway1first", u16::MAX),
+            token("2", "archway1first", 1),
+        ];
+        let participants = Participant::from_tokens(tokens, SocialScorePolicy::Sum);
+
+        assert_eq!(participants[0].social_score, u16::MAX);
+    }
+
+    #[test]
+    fn latest_policy_compares_ids_as_numbers() {
+        // token 10 is minted after token 9, although it sorts before it as a string
+        assert_eq!(
+            scores(SocialScorePolicy::Latest),
+            [("archway1first".into(), 30), ("archway1second".into(), 20)]
+        );
+    }
+}
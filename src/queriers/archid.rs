@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
@@ -6,6 +7,8 @@ use crate::prelude::*;
 #[serde(rename_all = "snake_case")]
 enum QueryMsg {
     ResolveAddress { address: String },
+    RecordExpiration { name: String },
+    Config {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -13,27 +16,120 @@ struct ResolveAddressResponse {
     pub names: Option<Vec<String>>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct RecordExpirationResponse {
+    pub expiration: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct ConfigResponse {
+    pub cw721: String,
+}
+
+/// Metadata of the ArchID tokens, which are named after their domain.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+struct Metadata {
+    #[serde(default)]
+    pub expiry: Option<u64>,
+}
+
+/// Registration of an ArchID name.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DomainRecord {
+    pub name: String,
+    pub expiration: Option<DateTime<Utc>>,
+    /// Whether the name is a subdomain, e.g. `app.domain.arch`.
+    pub subdomain: bool,
+}
+
+impl DomainRecord {
+    /// Whether the registration expired at the given time, which is never the case
+    /// when the expiration is unknown.
+    pub fn is_expired(&self, time: DateTime<Utc>) -> bool {
+        self.expiration.is_some_and(|expiration| expiration <= time)
+    }
+}
+
 pub struct ArchIdRegistry {
     ctx: Arc<Context>,
+    address: String,
 }
 
 impl ArchIdRegistry {
-    pub fn new(ctx: Arc<Context>) -> Self {
-        Self { ctx }
+    pub fn new(ctx: Arc<Context>) -> Result<Self> {
+        let address = ctx
+            .archid_address
+            .clone()
+            .ok_or(anyhow!("missing archid address"))?;
+
+        Ok(Self { ctx, address })
     }
 
     #[tracing::instrument(skip_all)]
     pub async fn resolve_domains(&self, address: String) -> Result<Vec<String>> {
-        tracing::debug!(%self.ctx.archid_address, "resolving ArchID names for address");
+        tracing::debug!(%self.address, "resolving ArchID names for address");
 
         let query = QueryMsg::ResolveAddress { address };
         let response: ResolveAddressResponse = self
             .ctx
-            .query_contract(self.ctx.archid_address.clone(), &query)
+            .query_contract(self.address.clone(), &query)
             .await?;
         let names = response.names.unwrap_or_default();
         tracing::debug!(count = names.len(), "found ArchID names");
 
         Ok(names)
     }
+
+    /// Address of the CW721 contract minting a token for each name.
+    #[tracing::instrument(skip_all)]
+    pub async fn cw721_address(&self) -> Result<String> {
+        let query = QueryMsg::Config {};
+        let response: ConfigResponse = self
+            .ctx
+            .query_contract(self.address.clone(), &query)
+            .await?;
+
+        Ok(response.cw721)
+    }
+
+    /// Record of a name, whose expiration comes from the registry for domains and
+    /// from the token metadata of the CW721 contract for subdomains.
+    #[tracing::instrument(skip(self, cw721))]
+    pub async fn record(&self, cw721: &str, name: String) -> Result<DomainRecord> {
+        // names are `domain.arch`, and subdomains are prefixed with one more label
+        let subdomain = name.split('.').count() > 2;
+
+        let expiration = if subdomain {
+            let query = cw721::Cw721QueryMsg::NftInfo {
+                token_id: name.clone(),
+            };
+            let response: cw721::NftInfoResponse<Option<Metadata>> =
+                self.ctx.query_contract(cw721.to_string(), &query).await?;
+            response.extension.unwrap_or_default().expiry
+        } else {
+            let query = QueryMsg::RecordExpiration { name: name.clone() };
+            let response: RecordExpirationResponse = self
+                .ctx
+                .query_contract(self.address.clone(), &query)
+                .await?;
+            Some(response.expiration)
+        };
+
+        let expiration = expiration
+            .map(|seconds| {
+                DateTime::from_timestamp(seconds as i64, 0).ok_or(anyhow!(
+                    "invalid expiration of {}: {}",
+                    name,
+                    seconds
+                ))
+            })
+            .transpose()?;
+        tracing::debug!(?expiration, subdomain, "found ArchID record");
+
+        Ok(DomainRecord {
+            name,
+            expiration,
+            subdomain,
+        })
+    }
 }
@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use bigdecimal::{num_bigint::BigUint, BigDecimal};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum QueryMsg {
+    Balance { address: String },
+    TokenInfo {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BalanceResponse {
+    pub balance: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokenInfoResponse {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub total_supply: String,
+}
+
+pub struct Cw20Token {
+    ctx: Arc<Context>,
+    address: String,
+    token_info: TokenInfoResponse,
+}
+
+impl Cw20Token {
+    pub async fn build(ctx: Arc<Context>, address: String) -> Result<Self> {
+        let token_info = Self::query_token_info(&ctx, &address).await?;
+        tracing::debug!(
+            address,
+            symbol = token_info.symbol,
+            decimals = token_info.decimals,
+            "building cw20 token"
+        );
+
+        Ok(Self {
+            ctx,
+            address,
+            token_info,
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn token_info(&self) -> &TokenInfoResponse {
+        &self.token_info
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn balance(&self, address: String) -> Result<BigDecimal> {
+        tracing::debug!(%self.address, "fetching cw20 token balance");
+
+        let query = QueryMsg::Balance { address };
+        let response: BalanceResponse = self
+            .ctx
+            .query_contract(self.address.clone(), &query)
+            .await?;
+        tracing::debug!(balance = response.balance, "cw20 token balance");
+
+        let balance_digits = BigUint::from_str(response.balance.as_str())?;
+        let balance = BigDecimal::new(balance_digits.into(), self.token_info.decimals as i64);
+
+        Ok(balance)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn query_token_info(ctx: &Arc<Context>, address: &str) -> Result<TokenInfoResponse> {
+        let query = QueryMsg::TokenInfo {};
+        ctx.query_contract(address.to_string(), &query).await
+    }
+}
@@ -0,0 +1,77 @@
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+const LIMIT: u32 = 100;
+
+/// Trait of the extension of a token following the ERC721 Metadata JSON Schema.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Trait {
+    pub trait_type: String,
+    pub value: serde_json::Value,
+}
+
+pub struct Cw721Collection {
+    ctx: Arc<Context>,
+    address: String,
+}
+
+impl Cw721Collection {
+    pub fn new(ctx: Arc<Context>, address: String) -> Self {
+        Self { ctx, address }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Ids of the tokens held by a single address.
+    #[tracing::instrument(skip(self))]
+    pub async fn tokens(&self, owner: &str) -> Result<Vec<String>> {
+        tracing::debug!(%self.address, "fetching cw721 tokens");
+
+        let mut tokens: Vec<String> = Vec::new();
+        let mut start_after: Option<String> = None;
+
+        loop {
+            let query = cw721::Cw721QueryMsg::Tokens {
+                owner: owner.to_string(),
+                start_after,
+                limit: Some(LIMIT),
+            };
+            let response: cw721::TokensResponse = self
+                .ctx
+                .query_contract(self.address.clone(), &query)
+                .await?;
+
+            // contracts may cap the limit below the requested one,
+            // so only an empty page marks the end of the tokens
+            if response.tokens.is_empty() {
+                break;
+            }
+
+            start_after = response.tokens.last().cloned();
+            tokens.extend(response.tokens);
+        }
+
+        tracing::debug!(count = tokens.len(), "found cw721 tokens");
+
+        Ok(tokens)
+    }
+
+    /// Traits of a token, empty when its extension has no valid attributes.
+    #[tracing::instrument(skip(self))]
+    pub async fn traits(&self, token_id: String) -> Result<Vec<Trait>> {
+        let query = cw721::Cw721QueryMsg::NftInfo { token_id };
+        let response: cw721::NftInfoResponse<serde_json::Value> = self
+            .ctx
+            .query_contract(self.address.clone(), &query)
+            .await?;
+
+        let Some(attributes) = response.extension.get("attributes") else {
+            return Ok(vec![]);
+        };
+
+        Ok(serde_json::from_value(attributes.clone()).unwrap_or_default())
+    }
+}
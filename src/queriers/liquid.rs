@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use bigdecimal::{num_bigint::BigUint, BigDecimal};
+use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
@@ -8,62 +8,72 @@ use crate::prelude::*;
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 enum QueryMsg {
-    Balance { address: String },
-    TokenInfo {},
+    State {},
+    Claims { address: String },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-pub struct BalanceResponse {
-    pub balance: String,
+struct StateResponse {
+    /// `ARCH` redeemed per `sARCH`, as a decimal string.
+    pub exchange_rate: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-pub struct TokenInfoResponse {
-    pub name: String,
-    pub symbol: String,
-    pub decimals: u8,
-    pub total_supply: String,
+struct Claim {
+    /// Amount of `aarch` to claim once unbonded.
+    pub amount: String,
 }
 
-pub struct LiquidFinanceCw20 {
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct ClaimsResponse {
+    pub claims: Vec<Claim>,
+}
+
+/// Staking contract of Liquid Finance, minting `sARCH` for the staked `ARCH`.
+pub struct LiquidFinanceStaking {
     ctx: Arc<Context>,
-    token_info: TokenInfoResponse,
+    address: String,
 }
 
-impl LiquidFinanceCw20 {
-    pub async fn build(ctx: Arc<Context>) -> Result<Self> {
-        let token_info = Self::token_info(&ctx).await?;
-        tracing::debug!(
-            address = ctx.liquid_finance_address,
-            symbol = token_info.symbol,
-            decimals = token_info.decimals,
-            "building cw20 token"
-        );
+impl LiquidFinanceStaking {
+    pub fn new(ctx: Arc<Context>) -> Result<Self> {
+        let address = ctx
+            .liquid_finance_staking_address
+            .clone()
+            .ok_or(anyhow!("missing liquid finance staking address"))?;
 
-        Ok(Self { ctx, token_info })
+        Ok(Self { ctx, address })
     }
 
+    /// Amount of `ARCH` redeemed per `sARCH`.
     #[tracing::instrument(skip_all)]
-    pub async fn balance(&self, address: String) -> Result<BigDecimal> {
-        tracing::debug!(%self.ctx.liquid_finance_address, "fetching cw20 token balance");
+    pub async fn exchange_rate(&self) -> Result<BigDecimal> {
+        tracing::debug!(%self.address, "fetching sARCH exchange rate");
 
-        let query = QueryMsg::Balance { address };
-        let response: BalanceResponse = self
+        let query = QueryMsg::State {};
+        let response: StateResponse = self
             .ctx
-            .query_contract(self.ctx.liquid_finance_address.clone(), &query)
+            .query_contract(self.address.clone(), &query)
             .await?;
-        tracing::debug!(balance = response.balance, "cw20 token balance");
-
-        let balance_digits = BigUint::from_str(response.balance.as_str())?;
-        let balance = BigDecimal::new(balance_digits.into(), self.token_info.decimals as i64);
+        tracing::debug!(rate = response.exchange_rate, "sARCH exchange rate");
 
-        Ok(balance)
+        Ok(BigDecimal::from_str(&response.exchange_rate)?)
     }
 
+    /// Total `ARCH` of the unbond claims of the address still held by the contract.
     #[tracing::instrument(skip_all)]
-    async fn token_info(ctx: &Arc<Context>) -> Result<TokenInfoResponse> {
-        let query = QueryMsg::TokenInfo {};
-        ctx.query_contract(ctx.liquid_finance_address.clone(), &query)
-            .await
+    pub async fn pending_claims(&self, address: String) -> Result<BigDecimal> {
+        let query = QueryMsg::Claims { address };
+        let response: ClaimsResponse = self
+            .ctx
+            .query_contract(self.address.clone(), &query)
+            .await?;
+        tracing::debug!(count = response.claims.len(), "pending unbond claims");
+
+        response
+            .claims
+            .iter()
+            .map(|claim| to_bigdecimal(&claim.amount))
+            .sum()
     }
 }
@@ -1,3 +1,5 @@
 pub mod archid;
+pub mod cw20;
+pub mod cw721;
 pub mod liquid;
 pub mod soulbound;
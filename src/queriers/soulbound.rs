@@ -19,18 +19,36 @@ pub struct TokenInfo {
     pub social_score: u16,
 }
 
+/// How the social score is computed for an address holding multiple tokens.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SocialScorePolicy {
+    /// Highest score among the tokens.
+    #[default]
+    Max,
+    /// Sum of the scores of all tokens.
+    Sum,
+    /// Score of the most recently minted token.
+    Latest,
+}
+
 pub struct SoulboundToken {
     ctx: Arc<Context>,
+    address: String,
 }
 
 impl SoulboundToken {
-    pub fn new(ctx: Arc<Context>) -> Self {
-        Self { ctx }
+    pub fn new(ctx: Arc<Context>) -> Result<Self> {
+        let address = ctx
+            .soulbound_address
+            .clone()
+            .ok_or(anyhow!("missing soulbound address"))?;
+
+        Ok(Self { ctx, address })
     }
 
     #[tracing::instrument(skip(self))]
     pub async fn all_tokens(&self) -> Result<Vec<TokenInfo>> {
-        tracing::info!(%self.ctx.soulbound_address, "querying soulbound token owners");
+        tracing::info!(%self.address, "querying soulbound token owners");
 
         let mut all_tokens: Vec<TokenInfo> = Vec::new();
         let mut start_after: Option<String> = None;
@@ -45,7 +63,7 @@ impl SoulboundToken {
             };
             let response: cw721::TokensResponse = self
                 .ctx
-                .query_contract(self.ctx.soulbound_address.clone(), &query)
+                .query_contract(self.address.clone(), &query)
                 .await?;
             let count = response.tokens.len();
             tracing::info!(%count, "found soulbound tokens");
@@ -70,6 +88,45 @@ impl SoulboundToken {
         Ok(all_tokens)
     }
 
+    /// Queries the tokens held by a single address.
+    #[tracing::instrument(skip(self))]
+    pub async fn tokens_of(&self, owner: &str) -> Result<Vec<TokenInfo>> {
+        let mut all_tokens: Vec<TokenInfo> = Vec::new();
+        let mut start_after: Option<String> = None;
+        let limit = 100;
+
+        loop {
+            let query = cw721::Cw721QueryMsg::Tokens {
+                owner: owner.to_string(),
+                start_after,
+                limit: Some(limit),
+            };
+            let response: cw721::TokensResponse = self
+                .ctx
+                .query_contract(self.address.clone(), &query)
+                .await?;
+            let count = response.tokens.len();
+
+            start_after = response.tokens.last().cloned();
+
+            let tokens: Vec<TokenInfo> = stream::iter(response.tokens)
+                .map(|token_id| self.token_info(token_id))
+                .buffer_unordered(10)
+                .try_collect()
+                .await?;
+
+            all_tokens.extend(tokens);
+
+            if count < limit as usize {
+                break;
+            }
+        }
+
+        tracing::info!(count = all_tokens.len(), "found soulbound tokens");
+
+        Ok(all_tokens)
+    }
+
     #[tracing::instrument(skip(self))]
     async fn token_info(&self, token_id: String) -> Result<TokenInfo> {
         tracing::debug!("querying soulbound token owner");
@@ -81,7 +138,7 @@ impl SoulboundToken {
 
         let response: cw721::AllNftInfoResponse<Extension> = self
             .ctx
-            .query_contract(self.ctx.soulbound_address.clone(), &query)
+            .query_contract(self.address.clone(), &query)
             .await?;
         let token = TokenInfo {
             id: token_id,
@@ -0,0 +1,365 @@
+use std::collections::{BTreeSet, HashMap};
+
+use futures::prelude::*;
+use tendermint_rpc::query::Query;
+
+use crate::csv;
+use crate::exporters::ErrorPolicy;
+use crate::participant::Participant;
+use crate::prelude::*;
+use crate::queriers::archid::ArchIdRegistry;
+
+/// Groups sharing a value with more addresses than this are ignored,
+/// as popular validators or funding from an exchange are not a sign of a farm.
+const MAX_GROUP_SIZE: usize = 20;
+
+/// Soulbound tokens minted at most this many blocks apart, about 6 seconds each,
+/// are considered received at the same time.
+const MINT_WINDOW: u64 = 1;
+
+/// A value shared by the addresses of a cluster.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Signal {
+    /// Funded by the same address.
+    Funder,
+    /// ArchID names following the same pattern, e.g. `farm1.arch` and `farm2.arch`.
+    ArchIdPattern,
+    /// Delegating to the same validators.
+    Validators,
+    /// Holding the same balances.
+    Balances,
+    /// Receiving the soulbound token within seconds of each other.
+    Mint,
+}
+
+impl Signal {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Funder => "funder",
+            Self::ArchIdPattern => "archid",
+            Self::Validators => "validators",
+            Self::Balances => "balances",
+            Self::Mint => "mint",
+        }
+    }
+
+    /// Likelihood of two addresses sharing the signal being controlled by the same person.
+    fn weight(&self) -> f32 {
+        match self {
+            Self::Funder => 0.5,
+            Self::ArchIdPattern => 0.3,
+            Self::Validators => 0.2,
+            Self::Balances => 0.4,
+            Self::Mint => 0.3,
+        }
+    }
+}
+
+/// On-chain traits of an address compared between participants.
+#[derive(Debug, Default)]
+struct Fingerprint {
+    funder: Option<String>,
+    archid_patterns: BTreeSet<String>,
+    validators: Option<String>,
+    balances: Option<String>,
+    mint_height: Option<u64>,
+}
+
+/// Looks for clusters of participants likely controlled by the same person,
+/// writing them to `sybil-clusters.csv` and setting the confidence of the
+/// clustered participants.
+pub async fn analyze(ctx: Arc<Context>, participants: &mut [Participant]) -> Result<()> {
+    tracing::info!(count = participants.len(), "starting sybil analysis");
+
+    let archid = match ctx.archid_address {
+        Some(_) => Some(ArchIdRegistry::new(ctx.clone())?),
+        None => None,
+    };
+
+    let results: Vec<(usize, Result<Fingerprint>)> = stream::iter(participants.iter().enumerate())
+        .map(|(i, participant)| {
+            let (ctx, archid) = (&ctx, &archid);
+            async move {
+                (
+                    i,
+                    fingerprint(ctx, archid.as_ref(), &participant.address).await,
+                )
+            }
+        })
+        .buffer_unordered(32)
+        .collect()
+        .await;
+
+    let mut fingerprints: Vec<Fingerprint> = Vec::new();
+    fingerprints.resize_with(participants.len(), Default::default);
+    for (i, result) in results {
+        fingerprints[i] = match result {
+            Err(err) if ctx.on_error == ErrorPolicy::Abort => return Err(err),
+            Err(err) => {
+                tracing::warn!(
+                    address = participants[i].address,
+                    "sybil fingerprint failed: {:#}",
+                    err
+                );
+                continue;
+            }
+            fingerprint => fingerprint?,
+        };
+    }
+
+    let mut clusters = Clusters::new(participants.len());
+    clusters.link(Signal::Funder, group(&fingerprints, |f| f.funder.iter()));
+    clusters.link(
+        Signal::ArchIdPattern,
+        group(&fingerprints, |f| f.archid_patterns.iter()),
+    );
+    clusters.link(
+        Signal::Validators,
+        group(&fingerprints, |f| f.validators.iter()),
+    );
+    clusters.link(
+        Signal::Balances,
+        group(&fingerprints, |f| f.balances.iter()),
+    );
+    clusters.link(Signal::Mint, mint_groups(&fingerprints));
+
+    let csv = ctx.csv_writer("sybil-clusters").await?;
+    let mut clustered = 0;
+    for (id, members) in clusters.components().into_iter().enumerate() {
+        for &i in members.iter() {
+            let signals = &clusters.signals[i];
+            let confidence = 1.0
+                - signals
+                    .iter()
+                    .map(|signal| 1.0 - signal.weight())
+                    .product::<f32>();
+            participants[i].sybil_confidence = Some(confidence);
+
+            csv.write(ClusterMember {
+                cluster: id + 1,
+                address: participants[i].address.clone(),
+                size: members.len(),
+                confidence,
+                signals: signals.iter().map(Signal::name).collect(),
+            })
+            .await?;
+        }
+        clustered += members.len();
+    }
+
+    tracing::info!(clustered, "sybil analysis finished");
+
+    Ok(())
+}
+
+async fn fingerprint(
+    ctx: &Context,
+    archid: Option<&ArchIdRegistry>,
+    address: &str,
+) -> Result<Fingerprint> {
+    let funder = ctx
+        .cosmos
+        .tx
+        .first_send(address)
+        .await?
+        .map(|transfer| transfer.sender);
+
+    let archid_patterns = match archid {
+        Some(archid) => archid
+            .resolve_domains(address.to_string())
+            .await?
+            .iter()
+            .filter_map(|name| name_pattern(name))
+            .collect(),
+        None => BTreeSet::new(),
+    };
+
+    let delegations = ctx.cosmos.staking.delegations(address.to_string()).await?;
+    let validators: BTreeSet<String> = delegations
+        .delegation_responses
+        .into_iter()
+        .filter_map(|response| response.delegation.map(|d| d.validator_address))
+        .collect();
+
+    let balances = ctx.cosmos.bank.balances(address.to_string()).await?;
+    let balances: BTreeSet<String> = balances
+        .balances
+        .into_iter()
+        .map(|coin| format!("{}{}", coin.amount, coin.denom))
+        .collect();
+
+    let mint_height = match &ctx.soulbound_address {
+        Some(soulbound) => {
+            let query = Query::eq("wasm._contract_address", soulbound.as_str())
+                .and_eq("wasm.action", "mint")
+                .and_eq("wasm.owner", address);
+            ctx.cosmos
+                .tx
+                .first(query)
+                .await?
+                .map(|tx| tx.height.value())
+        }
+        None => None,
+    };
+
+    let join = |values: BTreeSet<String>| {
+        (!values.is_empty()).then(|| values.into_iter().collect::<Vec<_>>().join(","))
+    };
+
+    Ok(Fingerprint {
+        funder,
+        archid_patterns,
+        validators: join(validators),
+        balances: join(balances),
+        mint_height,
+    })
+}
+
+/// Pattern of a name ending with a number, e.g. `farm` for `farm12.arch`.
+fn name_pattern(name: &str) -> Option<String> {
+    let label = name.split('.').next().unwrap_or_default();
+    let stem = label.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    (stem.len() < label.len() && !stem.is_empty()).then(|| stem.to_string())
+}
+
+/// Indexes of the fingerprints sharing each value.
+fn group<'a, F, I>(fingerprints: &'a [Fingerprint], values: F) -> Vec<Vec<usize>>
+where
+    F: Fn(&'a Fingerprint) -> I,
+    I: Iterator<Item = &'a String>,
+{
+    let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, fingerprint) in fingerprints.iter().enumerate() {
+        for value in values(fingerprint) {
+            groups.entry(value).or_default().push(i);
+        }
+    }
+
+    groups.into_values().collect()
+}
+
+/// Indexes of the fingerprints with soulbound tokens minted within a window
+/// starting at the first mint of each group, so a steady stream of mints is split
+/// into separate groups instead of chaining into one.
+fn mint_groups(fingerprints: &[Fingerprint]) -> Vec<Vec<usize>> {
+    let mut mints: Vec<(u64, usize)> = fingerprints
+        .iter()
+        .enumerate()
+        .filter_map(|(i, fingerprint)| fingerprint.mint_height.map(|height| (height, i)))
+        .collect();
+    mints.sort();
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut first_height = None;
+    for (height, i) in mints {
+        match (first_height, groups.last_mut()) {
+            (Some(first), Some(group)) if height - first <= MINT_WINDOW => group.push(i),
+            _ => {
+                groups.push(vec![i]);
+                first_height = Some(height);
+            }
+        }
+    }
+
+    groups
+}
+
+/// Union-find over the participant indexes, remembering the signals linking each of them.
+struct Clusters {
+    parents: Vec<usize>,
+    signals: Vec<BTreeSet<Signal>>,
+}
+
+impl Clusters {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            signals: vec![BTreeSet::new(); len],
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        // path compression
+        let mut i = i;
+        while self.parents[i] != root {
+            let next = self.parents[i];
+            self.parents[i] = root;
+            i = next;
+        }
+
+        root
+    }
+
+    fn link(&mut self, signal: Signal, groups: Vec<Vec<usize>>) {
+        for group in groups {
+            if group.len() < 2 {
+                continue;
+            }
+            if group.len() > MAX_GROUP_SIZE {
+                tracing::debug!(
+                    signal = signal.name(),
+                    size = group.len(),
+                    "ignoring large group"
+                );
+                continue;
+            }
+
+            let root = self.find(group[0]);
+            for &i in group.iter() {
+                let other = self.find(i);
+                self.parents[other] = root;
+                self.signals[i].insert(signal);
+            }
+        }
+    }
+
+    /// Indexes of each cluster with more than one address, in participant order.
+    fn components(&mut self) -> Vec<Vec<usize>> {
+        let mut index: HashMap<usize, usize> = HashMap::new();
+        let mut components: Vec<Vec<usize>> = Vec::new();
+
+        for i in 0..self.parents.len() {
+            let root = self.find(i);
+            match index.get(&root) {
+                Some(&c) => components[c].push(i),
+                None => {
+                    index.insert(root, components.len());
+                    components.push(vec![i]);
+                }
+            }
+        }
+
+        components.retain(|members| members.len() > 1);
+        components
+    }
+}
+
+pub struct ClusterMember {
+    cluster: usize,
+    address: String,
+    size: usize,
+    confidence: f32,
+    signals: Vec<&'static str>,
+}
+
+impl csv::Item for ClusterMember {
+    fn header() -> csv::Header {
+        vec!["cluster", "address", "size", "confidence", "signals"]
+    }
+
+    fn rows(self) -> Vec<csv::Row> {
+        vec![vec![
+            self.cluster.to_string(),
+            self.address,
+            self.size.to_string(),
+            format!("{:.2}", self.confidence),
+            self.signals.join(","),
+        ]]
+    }
+}